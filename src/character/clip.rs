@@ -0,0 +1,82 @@
+use super::CharacterState;
+use bevy::prelude::*;
+use futures::{
+    future::{self, LocalBoxFuture},
+    FutureExt,
+};
+use serde::Deserialize;
+use std::{collections::HashMap, time::Duration};
+
+/// Animation that runs on a [`CharacterState`].
+///
+/// Times are relative to the duration a clip is played with, so the same clip can be played
+/// faster or slower.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub enum Clip {
+    /// Rotate joints to angles, in radians, scaled by the amplitude the clip is played with.
+    ///
+    /// Joints the character's rig doesn't have are ignored.
+    Pose {
+        joints: HashMap<String, f32>,
+        time: f32,
+    },
+    /// Turn the character to face `yaw` radians from forward.
+    Face { yaw: f32, time: f32 },
+    /// Play each clip after the last one finishes.
+    Sequence(Vec<Clip>),
+    /// Play every clip at once, finishing with the longest one.
+    Parallel(Vec<Clip>),
+}
+
+impl Clip {
+    /// Play this clip on `character`, where a `time` of 1 lasts `duration`.
+    pub fn play<'a>(
+        &'a self,
+        character: &'a CharacterState,
+        duration: Duration,
+        amplitude: f32,
+    ) -> LocalBoxFuture<'a, ()> {
+        match self {
+            Self::Pose { joints, time } => future::join_all(
+                joints
+                    .iter()
+                    .filter_map(|(name, angle)| Some((character.joints.get(name)?, angle)))
+                    .map(move |(joint, angle)| {
+                        joint.animate(angle * amplitude, duration.mul_f32(*time))
+                    }),
+            )
+            .map(|_| ())
+            .boxed_local(),
+            Self::Face { yaw, time } => character
+                .rotation
+                .animate(Vec3::new(0., *yaw, 0.), duration.mul_f32(*time))
+                .boxed_local(),
+            Self::Sequence(clips) => async move {
+                for clip in clips {
+                    clip.play(character, duration, amplitude).await;
+                }
+            }
+            .boxed_local(),
+            Self::Parallel(clips) => future::join_all(
+                clips
+                    .iter()
+                    .map(|clip| clip.play(character, duration, amplitude)),
+            )
+            .map(|_| ())
+            .boxed_local(),
+        }
+    }
+}
+
+/// Named [`Clip`]s, loaded from a `.clips.ron` file.
+#[derive(Asset, TypePath, Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct ClipLibrary {
+    clips: HashMap<String, Clip>,
+}
+
+impl ClipLibrary {
+    pub fn get(&self, name: &str) -> Option<&Clip> {
+        self.clips.get(name)
+    }
+}
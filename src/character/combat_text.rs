@@ -0,0 +1,92 @@
+use super::EffectKind;
+use actuate::prelude::*;
+use bevy::prelude::*;
+
+/// Seconds combat text stays on screen.
+pub const LIFETIME: f32 = 1.;
+
+/// Event shown as text floating above a character.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CombatText {
+    Damage(u32),
+    Heal(u32),
+    // Shown once attacks can miss or land critical hits.
+    #[allow(dead_code)]
+    Miss,
+    #[allow(dead_code)]
+    Crit,
+    /// A status effect was applied.
+    Status(EffectKind),
+}
+
+impl CombatText {
+    fn label(self) -> String {
+        match self {
+            Self::Damage(amount) => amount.to_string(),
+            Self::Heal(amount) => format!("+{amount}"),
+            Self::Miss => String::from("Miss"),
+            Self::Crit => String::from("Critical!"),
+            Self::Status(kind) => kind.name().to_owned(),
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            Self::Damage(_) => Color::srgb_u8(255, 82, 82),
+            Self::Heal(_) => Color::srgb_u8(105, 240, 174),
+            Self::Miss => Color::srgb_u8(189, 189, 189),
+            Self::Crit => Color::srgb_u8(255, 235, 59),
+            Self::Status(_) => Color::srgb_u8(128, 216, 255),
+        }
+    }
+
+    fn font_size(self) -> f32 {
+        match self {
+            Self::Crit => 2.5,
+            Self::Damage(_) | Self::Heal(_) => 2.,
+            Self::Miss => 1.5,
+            Self::Status(_) => 1.25,
+        }
+    }
+}
+
+/// [`CombatText`] that rises and fades out over its [`LIFETIME`].
+#[derive(Data)]
+pub struct FloatingText {
+    pub text: CombatText,
+    /// Seconds since the text appeared.
+    pub age: f32,
+    /// Position the text starts rising from.
+    pub pos: Vec2,
+}
+
+impl Compose for FloatingText {
+    fn compose(cx: Scope<Self>) -> impl Compose {
+        let entity = use_context::<Entity>(&cx).unwrap();
+
+        let font = use_world_once(&cx, |asset_server: Res<AssetServer>| {
+            asset_server.load("C&C Red Alert [INET].ttf")
+        });
+
+        let progress = (cx.me().age / LIFETIME).clamp(0., 1.);
+        let text = cx.me().text;
+
+        spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(cx.me().pos.y - progress * 4.),
+                left: Val::Px(cx.me().pos.x),
+                ..default()
+            },
+            Text::new(text.label()),
+            TextColor(text.color().with_alpha(1. - progress)),
+            TextFont {
+                font: font.clone(),
+                font_size: text.font_size(),
+                ..default()
+            },
+            PickingBehavior::IGNORE,
+        ))
+        .target(*entity)
+    }
+}
@@ -0,0 +1,32 @@
+use super::{BaseStats, Modifier, Rig, StatBlock};
+use bevy::prelude::*;
+use serde::Deserialize;
+
+/// Definition of a playable character, loaded from a `.character.ron` file.
+#[derive(Asset, TypePath, Clone, Debug, PartialEq, Deserialize)]
+pub struct CharacterDef {
+    /// Display name of the character.
+    pub name: String,
+    /// Path to the character's .vox model.
+    pub model: String,
+    /// Skeleton of the model, defaulting to [`Rig::humanoid`].
+    #[serde(default = "Rig::humanoid")]
+    pub rig: Rig,
+    pub stats: BaseStats,
+    /// Damage absorbed before health at the start of a battle.
+    #[serde(default)]
+    pub shield: u32,
+    /// Modifiers from the character's equipment.
+    #[serde(default)]
+    pub modifiers: Vec<Modifier>,
+    /// Names of the skills this character can use, in slot order.
+    #[serde(default)]
+    pub skills: Vec<String>,
+}
+
+impl CharacterDef {
+    /// Create the stat block this character starts a battle with.
+    pub fn stat_block(&self) -> StatBlock {
+        StatBlock::new(self.stats.clone(), self.modifiers.clone())
+    }
+}
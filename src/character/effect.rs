@@ -0,0 +1,131 @@
+use super::{Modifier, Stat};
+use serde::Deserialize;
+
+/// Kind of [`StatusEffect`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub enum EffectKind {
+    /// Slows the character, stacking up to 3 times.
+    Chilled,
+    /// Skips the character's turns.
+    Frozen,
+    /// Deals damage at the start of each turn, stacking up to 3 times.
+    Burning,
+    /// Deals damage at the start of each turn, stacking up to 5 times.
+    Poisoned,
+    /// Skips the character's turns.
+    Stunned,
+    /// Speeds up the character.
+    Haste,
+}
+
+/// How a status effect combines with itself when it's applied again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stacking {
+    /// Reset the remaining duration if the new one is longer.
+    Refresh,
+    /// Add a stack, up to `max`, and reset the remaining duration.
+    Intensify { max: u32 },
+}
+
+/// Point in a turn where a status effect ticks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TickPhase {
+    TurnStart,
+    TurnEnd,
+}
+
+impl EffectKind {
+    pub fn stacking(self) -> Stacking {
+        match self {
+            Self::Chilled | Self::Burning => Stacking::Intensify { max: 3 },
+            Self::Poisoned => Stacking::Intensify { max: 5 },
+            Self::Frozen | Self::Stunned | Self::Haste => Stacking::Refresh,
+        }
+    }
+
+    pub fn tick_phase(self) -> TickPhase {
+        match self {
+            Self::Burning | Self::Poisoned => TickPhase::TurnStart,
+            Self::Chilled | Self::Frozen | Self::Stunned | Self::Haste => TickPhase::TurnEnd,
+        }
+    }
+
+    /// Returns `true` if a character with this effect loses their turns.
+    pub fn skips_turn(self) -> bool {
+        matches!(self, Self::Frozen | Self::Stunned)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Chilled => "Chilled",
+            Self::Frozen => "Frozen",
+            Self::Burning => "Burning",
+            Self::Poisoned => "Poisoned",
+            Self::Stunned => "Stunned",
+            Self::Haste => "Haste",
+        }
+    }
+
+    /// Path to the icon shown in a character's status bar.
+    pub fn icon(self) -> &'static str {
+        match self {
+            Self::Chilled => "effects/chilled.png",
+            Self::Frozen => "effects/frozen.png",
+            Self::Burning => "effects/burning.png",
+            Self::Poisoned => "effects/poisoned.png",
+            Self::Stunned => "effects/stunned.png",
+            Self::Haste => "effects/haste.png",
+        }
+    }
+}
+
+/// Status effect on a character, lasting a number of turns.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StatusEffect {
+    pub kind: EffectKind,
+    /// Turns left until this effect wears off.
+    pub turns: u32,
+    pub stacks: u32,
+}
+
+impl StatusEffect {
+    pub fn new(kind: EffectKind, turns: u32) -> Self {
+        Self {
+            kind,
+            turns,
+            stacks: 1,
+        }
+    }
+
+    /// Modifier this effect applies to its character's stats, if any.
+    pub fn modifier(&self) -> Option<Modifier> {
+        match self.kind {
+            EffectKind::Chilled => Some(Modifier::Multiply(
+                Stat::Speed,
+                0.8f32.powi(self.stacks as i32),
+            )),
+            EffectKind::Haste => Some(Modifier::Multiply(Stat::Speed, 1.5)),
+            _ => None,
+        }
+    }
+
+    /// Damage this effect deals each time it ticks.
+    pub fn damage(&self) -> u32 {
+        match self.kind {
+            EffectKind::Burning => 4 * self.stacks,
+            EffectKind::Poisoned => 3 * self.stacks,
+            _ => 0,
+        }
+    }
+
+    /// Apply this effect again, following its [`Stacking`] rule.
+    pub fn stack(&mut self, turns: u32) {
+        match self.kind.stacking() {
+            Stacking::Refresh => self.turns = self.turns.max(turns),
+            Stacking::Intensify { max } => {
+                self.stacks = (self.stacks + 1).min(max);
+                self.turns = turns;
+            }
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use bevy::asset::{io::Reader, Asset, AssetLoader, LoadContext};
+use serde::de::DeserializeOwned;
+use std::{error::Error, fmt, io, marker::PhantomData};
+
+/// Error loading a RON asset.
+#[derive(Debug)]
+pub enum RonLoaderError {
+    Io(io::Error),
+    Ron(ron::error::SpannedError),
+}
+
+impl fmt::Display for RonLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "failed to read asset: {error}"),
+            Self::Ron(error) => write!(f, "failed to parse asset: {error}"),
+        }
+    }
+}
+
+impl Error for RonLoaderError {}
+
+impl From<io::Error> for RonLoaderError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<ron::error::SpannedError> for RonLoaderError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        Self::Ron(error)
+    }
+}
+
+/// Asset loader for assets written in RON.
+pub struct RonLoader<A> {
+    extensions: &'static [&'static str],
+    _marker: PhantomData<fn() -> A>,
+}
+
+impl<A> RonLoader<A> {
+    /// Create a loader for files ending in one of `extensions`.
+    pub fn new(extensions: &'static [&'static str]) -> Self {
+        Self {
+            extensions,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<A: Asset + DeserializeOwned> AssetLoader for RonLoader<A> {
+    type Asset = A;
+    type Settings = ();
+    type Error = RonLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.extensions
+    }
+}
@@ -0,0 +1,638 @@
+use crate::queue::{use_skip, Skip, SkippableController};
+use actuate::{animation::AnimationController, compose, prelude::*};
+use bevy::prelude::*;
+use futures::future;
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+    f32::consts::FRAC_PI_2,
+    mem,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use voxy::{scene::VoxelSceneHandle, VoxelSceneModels};
+
+mod clip;
+pub use self::clip::ClipLibrary;
+
+mod combat_text;
+pub use self::combat_text::CombatText;
+use self::combat_text::{FloatingText, LIFETIME};
+
+mod definition;
+pub use self::definition::CharacterDef;
+
+mod effect;
+use self::effect::TickPhase;
+pub use self::effect::{EffectKind, StatusEffect};
+
+mod loader;
+use self::loader::RonLoader;
+
+mod registry;
+pub use self::registry::{CharacterId, Characters};
+
+mod rig;
+pub use self::rig::Rig;
+
+mod roster;
+pub use self::roster::Roster;
+
+mod status_bar;
+use self::status_bar::StatusBar;
+pub use self::status_bar::StatusBarSettings;
+
+mod stats;
+pub use self::stats::{scale_damage, BaseStats, Modifier, Stat, StatBlock};
+
+mod team;
+pub use self::team::{Allegiance, Team};
+
+/// Plugin to load character definitions, rosters and animation clips.
+pub struct CharacterPlugin;
+
+impl Plugin for CharacterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<CharacterDef>()
+            .init_asset::<ClipLibrary>()
+            .init_asset::<Roster>()
+            .register_asset_loader(RonLoader::<CharacterDef>::new(&["character.ron"]))
+            .register_asset_loader(RonLoader::<ClipLibrary>::new(&["clips.ron"]))
+            .register_asset_loader(RonLoader::<Roster>::new(&["roster.ron"]))
+            .init_resource::<StatusBarSettings>();
+    }
+}
+
+#[derive(Clone, Data)]
+pub struct CharacterState {
+    pub name: String,
+    pub team: Team,
+    pub translation: SkippableController<Vec3>,
+    pub rotation: SkippableController<Vec3>,
+    /// Strength of the tint flashed over the model when it's hit, from 0 to 1.
+    pub flash: SkippableController<f32>,
+    /// Rotation of each joint in this character's [`Rig`], by name.
+    pub joints: HashMap<String, SkippableController<f32>>,
+    pub home: Vec3,
+    pub stats: StatBlock,
+    /// Current health, up to the [`Stat::MaxHealth`] stat.
+    pub health: u32,
+    /// Current energy, up to the [`Stat::MaxEnergy`] stat.
+    pub energy: u32,
+    /// Damage absorbed before health.
+    pub shield: u32,
+    pub effects: Vec<StatusEffect>,
+    /// Animation clips this character can play.
+    pub clips: Arc<ClipLibrary>,
+    /// Combat text waiting to be shown above this character.
+    pub combat_text: Arc<Mutex<Vec<CombatText>>>,
+}
+
+/// Controllers that animate a character's model, from `use_animated`.
+pub struct CharacterAnimation {
+    pub translation: AnimationController<Vec3>,
+    pub rotation: AnimationController<Vec3>,
+    pub flash: AnimationController<f32>,
+    /// Controller for each joint in the character's [`Rig`], with the joint's name.
+    pub joints: Vec<(String, AnimationController<f32>)>,
+}
+
+impl CharacterState {
+    /// Create the state of a character standing at `home` with full health and energy.
+    pub fn new(
+        name: String,
+        team: Team,
+        home: Vec3,
+        stats: StatBlock,
+        clips: Arc<ClipLibrary>,
+        animation: CharacterAnimation,
+        skip: &Skip,
+    ) -> Self {
+        Self {
+            name,
+            team,
+            translation: SkippableController::new(animation.translation, skip.clone(), home),
+            rotation: SkippableController::new(animation.rotation, skip.clone(), Vec3::ZERO),
+            flash: SkippableController::new(animation.flash, skip.clone(), 0.),
+            joints: animation
+                .joints
+                .into_iter()
+                .map(|(name, controller)| {
+                    (name, SkippableController::new(controller, skip.clone(), 0.))
+                })
+                .collect(),
+            home,
+            health: stats.get(Stat::MaxHealth),
+            energy: stats.get(Stat::MaxEnergy),
+            shield: 0,
+            stats,
+            effects: Vec::new(),
+            clips,
+            combat_text: Arc::default(),
+        }
+    }
+
+    /// Play the clip named `name` from this character's [`ClipLibrary`].
+    ///
+    /// A clip time of 1 lasts `duration`, and joint angles are scaled by `amplitude`.
+    pub async fn play(&self, name: &str, duration: Duration, amplitude: f32) {
+        match self.clips.get(name) {
+            Some(clip) => clip.play(self, duration, amplitude).await,
+            None => warn!("No animation clip named `{name}`"),
+        }
+    }
+
+    /// Take `amount` damage, absorbed by this character's shield before their health.
+    pub fn take_damage(&mut self, amount: u32) {
+        let absorbed = amount.min(self.shield);
+        self.shield -= absorbed;
+        self.health = self.health.saturating_sub(amount - absorbed);
+    }
+
+    /// Show `text` floating above this character.
+    ///
+    /// Changes in health are shown automatically.
+    pub fn show(&self, text: CombatText) {
+        self.combat_text.lock().unwrap().push(text);
+    }
+
+    /// Get the value of `stat` with this character's status effects applied.
+    pub fn stat(&self, stat: Stat) -> u32 {
+        self.stats
+            .get_with(stat, self.effects.iter().filter_map(StatusEffect::modifier))
+    }
+
+    /// Apply a status effect for `turns` turns, stacking it with any of the same kind.
+    pub fn apply_effect(&mut self, kind: EffectKind, turns: u32) {
+        self.show(CombatText::Status(kind));
+
+        if let Some(effect) = self.effects.iter_mut().find(|effect| effect.kind == kind) {
+            effect.stack(turns);
+        } else {
+            self.effects.push(StatusEffect::new(kind, turns));
+        }
+    }
+
+    /// Start this character's turn, regenerating energy and ticking status effects.
+    pub fn start_turn(&mut self) {
+        self.energy = (self.energy + self.stat(Stat::EnergyRegen)).min(self.stat(Stat::MaxEnergy));
+        self.tick_effects(TickPhase::TurnStart);
+    }
+
+    /// End this character's turn, ticking status effects.
+    pub fn end_turn(&mut self) {
+        self.tick_effects(TickPhase::TurnEnd);
+    }
+
+    /// Tick each status effect that ticks in `phase`, dealing its damage and removing it once
+    /// it wears off.
+    fn tick_effects(&mut self, phase: TickPhase) {
+        let mut damage = 0;
+        for effect in &mut self.effects {
+            if effect.kind.tick_phase() == phase {
+                damage += effect.damage();
+                effect.turns = effect.turns.saturating_sub(1);
+            }
+        }
+        self.take_damage(damage);
+
+        self.effects.retain(|effect| effect.turns > 0);
+    }
+
+    /// Get how this character sees `other`.
+    pub fn allegiance(&self, other: &CharacterState) -> Allegiance {
+        self.team.allegiance(other.team)
+    }
+
+    /// Returns `true` if this character can act this turn.
+    pub fn can_act(&self) -> bool {
+        !self.is_defeated() && !self.effects.iter().any(|effect| effect.kind.skips_turn())
+    }
+
+    /// Returns `true` if this character has no health left.
+    ///
+    /// Defeated characters can't act or be targeted.
+    pub fn is_defeated(&self) -> bool {
+        self.health == 0
+    }
+
+    /// Animate this character falling over after being defeated.
+    pub async fn collapse(&self) {
+        let duration = Duration::from_millis(600);
+
+        future::join(
+            self.rotation
+                .animate(Vec3::new(-FRAC_PI_2, 0., 0.), duration),
+            future::join_all(
+                self.joints
+                    .values()
+                    .map(|joint| joint.animate(0., duration)),
+            ),
+        )
+        .await;
+    }
+
+    /// React to being hit by an attack from `from`, recoiling and flinching with a tint flash.
+    pub async fn react_to_hit(&self, from: Vec3) {
+        let position = self.translation.get();
+        let rotation = self.rotation.get();
+        let away = (position - from).normalize_or_zero();
+
+        let recoil = Duration::from_millis(100);
+        let recover = Duration::from_millis(300);
+
+        future::join3(
+            async {
+                future::join(
+                    self.translation.animate(position + away * 4., recoil),
+                    self.rotation
+                        .animate(rotation + Vec3::new(0.3, 0., 0.), recoil),
+                )
+                .await;
+
+                future::join(
+                    self.translation.animate(position, recover),
+                    self.rotation.animate(rotation, recover),
+                )
+                .await;
+            },
+            self.play("flinch", recoil + recover, 1.),
+            async {
+                self.flash.animate(1., Duration::from_millis(50)).await;
+                self.flash.animate(0., Duration::from_millis(250)).await;
+            },
+        )
+        .await;
+    }
+
+    /// Turn this character to face `point`.
+    pub async fn face(&self, point: Vec3, duration: Duration) {
+        // Characters face -Z when they aren't rotated.
+        let direction = point - self.translation.get();
+        let yaw = f32::atan2(-direction.x, -direction.z);
+
+        self.rotation
+            .animate(Vec3::new(0., yaw, 0.), duration)
+            .await;
+    }
+
+    /// Animate this character back to its resting pose at its home position, clearing any
+    /// hit flash.
+    pub async fn rest(&self, duration: Duration) {
+        future::join4(
+            self.translation.animate(self.home, duration),
+            self.rotation.animate(Vec3::ZERO, duration),
+            self.flash.animate(0., duration),
+            future::join_all(
+                self.joints
+                    .values()
+                    .map(|joint| joint.animate(0., duration)),
+            ),
+        )
+        .await;
+    }
+}
+
+#[derive(Data)]
+pub struct Character<'a> {
+    pub id: CharacterId,
+    pub target: Option<CharacterId>,
+    pub transation: Vec3,
+    pub name: String,
+    pub team: Team,
+    /// Path to the character's .vox model.
+    pub model: String,
+    pub rig: Rig,
+    pub stats: StatBlock,
+    pub clips: Arc<ClipLibrary>,
+    pub on_mount: Box<dyn Fn(CharacterState) + 'a>,
+    pub on_click: Box<dyn Fn() + Send + Sync + 'a>,
+    pub health: u32,
+    pub energy: u32,
+    pub shield: u32,
+    pub effects: Vec<StatusEffect>,
+}
+
+impl Compose for Character<'_> {
+    fn compose(cx: Scope<Self>) -> impl Compose {
+        let translation = use_animated(&cx, || cx.me().transation);
+        let rotation = use_animated(&cx, || Vec3::ZERO);
+        let flash = use_animated(&cx, || 0.);
+
+        // The rig is fixed once a character is mounted, so this always uses the same hooks.
+        let joints: Vec<_> = cx
+            .me()
+            .rig
+            .joints
+            .iter()
+            .map(|_| use_animated(&cx, || 0.))
+            .collect();
+
+        let skip = use_skip(&cx);
+
+        let state = use_ref(&cx, || {
+            let animation = CharacterAnimation {
+                translation: translation.controller(),
+                rotation: rotation.controller(),
+                flash: flash.controller(),
+                joints: cx
+                    .me()
+                    .rig
+                    .joints
+                    .iter()
+                    .zip(&joints)
+                    .map(|(joint, animated)| (joint.name.clone(), animated.controller()))
+                    .collect(),
+            };
+            let state = CharacterState {
+                health: cx.me().health,
+                energy: cx.me().energy,
+                shield: cx.me().shield,
+                ..CharacterState::new(
+                    cx.me().name.clone(),
+                    cx.me().team,
+                    cx.me().transation,
+                    cx.me().stats.clone(),
+                    cx.me().clips.clone(),
+                    animation,
+                    skip,
+                )
+            };
+            (cx.me().on_mount)(state.clone());
+            state
+        });
+
+        // Skills move relative to where characters were last drawn.
+        state.translation.set_rendered(*translation);
+        state.rotation.set_rendered(*rotation);
+
+        let on_click = Signal::map(cx.me(), |me| &me.on_click);
+
+        let stat = |stat| {
+            cx.me().stats.get_with(
+                stat,
+                cx.me().effects.iter().filter_map(StatusEffect::modifier),
+            )
+        };
+
+        CharacterModel {
+            transform: Transform::from_translation(*translation).with_rotation(Quat::from_euler(
+                EulerRot::YXZ,
+                rotation.y,
+                rotation.x,
+                rotation.z,
+            )),
+            model: cx.me().model.clone(),
+            rig: cx.me().rig.clone(),
+            joint_rotations: joints.iter().map(|animated| **animated).collect(),
+            flash: *flash,
+            combat_text: state.combat_text.clone(),
+            health: cx.me().health,
+            max_health: stat(Stat::MaxHealth),
+            shield: cx.me().shield,
+            energy: cx.me().energy,
+            max_energy: stat(Stat::MaxEnergy),
+            effects: cx.me().effects.clone(),
+            is_selected: cx.me().target == Some(cx.me().id),
+            on_click: Box::new(move || (on_click)()),
+        }
+    }
+}
+
+/// Emissive color of a character's materials at the peak of a hit flash.
+const FLASH_COLOR: LinearRgba = LinearRgba::rgb(2.4, 3.2, 4.);
+
+#[derive(Data)]
+pub struct CharacterModel<'a> {
+    pub transform: Transform,
+    /// Path to the .vox model.
+    pub model: String,
+    pub rig: Rig,
+    /// Rotation of each joint in `rig`, in the same order.
+    pub joint_rotations: Vec<f32>,
+    /// Strength of the hit flash, from 0 to 1.
+    pub flash: f32,
+    pub combat_text: Arc<Mutex<Vec<CombatText>>>,
+    pub health: u32,
+    pub max_health: u32,
+    pub shield: u32,
+    pub energy: u32,
+    pub max_energy: u32,
+    pub effects: Vec<StatusEffect>,
+    pub is_selected: bool,
+    pub on_click: Box<dyn Fn() + Send + Sync + 'a>,
+}
+
+impl Compose for CharacterModel<'_> {
+    fn compose(cx: Scope<Self>) -> impl Compose {
+        let handle = use_world_once(&cx, |asset_server: Res<AssetServer>| {
+            asset_server.load(cx.me().model.clone())
+        });
+
+        let entity = use_bundle(&cx, || {
+            (
+                VoxelSceneHandle(handle.clone()),
+                cx.me().transform,
+                Visibility::default(),
+            )
+        });
+
+        let on_click: Map<'_, Box<dyn Fn() + Send + Sync>> =
+            Signal::map(cx.me(), |me| &me.on_click);
+        let on_click: Map<'_, Box<dyn Fn() + Send + Sync>> = unsafe { mem::transmute(on_click) };
+
+        let is_loaded = use_ref(&cx, || Cell::new(false));
+
+        let is_mouse_down = Arc::new(Mutex::new(false));
+
+        use_world(
+            &cx,
+            move |mut commands: Commands, query: Query<&Children>| {
+                let is_mouse_down = is_mouse_down.clone();
+
+                if !is_loaded.get() {
+                    for child in query.get(entity).into_iter().flatten() {
+                        is_loaded.set(true);
+
+                        let is_mouse_down = is_mouse_down.clone();
+                        let is_mouse_down_out = is_mouse_down.clone();
+                        let is_mouse_down_up = is_mouse_down.clone();
+                        commands
+                            .entity(*child)
+                            .observe(move |_trigger: Trigger<Pointer<Down>>| {
+                                *is_mouse_down.lock().unwrap() = true;
+                            })
+                            .observe(move |_trigger: Trigger<Pointer<Out>>| {
+                                *is_mouse_down_out.lock().unwrap() = false;
+                            })
+                            .observe(move |_trigger: Trigger<Pointer<Up>>| {
+                                if mem::take(&mut *is_mouse_down_up.lock().unwrap()) {
+                                    on_click()
+                                }
+                            });
+                    }
+                }
+            },
+        );
+
+        let pos = use_mut(&cx, || Vec2::ZERO);
+
+        use_world(
+            &cx,
+            move |query: Query<(&Camera, &GlobalTransform)>,
+                  q: Query<&GlobalTransform, Without<Camera>>,
+                  ui_scale: Res<UiScale>| {
+                let world_position = q.get(entity).unwrap().translation();
+
+                let (camera, camera_transform) = query.single();
+                let screen_pos = camera
+                    .world_to_viewport(camera_transform, world_position)
+                    .unwrap_or_default();
+                SignalMut::set(pos, screen_pos / **ui_scale);
+            },
+        );
+
+        // Combat text floating above this character, with its age and line.
+        let floating = use_mut(&cx, Vec::new);
+        let last_health = use_ref(&cx, || Cell::new(None));
+
+        use_world(&cx, move |time: Res<Time>| {
+            let mut texts = mem::take(&mut *cx.me().combat_text.lock().unwrap());
+
+            // Damage absorbed by a shield is still shown as damage.
+            let health = cx.me().health;
+            let total = health + cx.me().shield;
+            if let Some((last_health, last_total)) = last_health.replace(Some((health, total))) {
+                if total < last_total {
+                    texts.push(CombatText::Damage(last_total - total));
+                } else if health > last_health {
+                    texts.push(CombatText::Heal(health - last_health));
+                }
+            }
+
+            if texts.is_empty() && floating.is_empty() {
+                return;
+            }
+
+            let delta = time.delta_secs();
+            SignalMut::update(floating, move |floating| {
+                for (_, age, _) in floating.iter_mut() {
+                    *age += delta;
+                }
+                floating.retain(|(_, age, _)| *age < LIFETIME);
+
+                // Text shown at the same time is stacked in lines.
+                floating.extend(
+                    texts
+                        .into_iter()
+                        .enumerate()
+                        .map(|(line, text)| (text, 0., line)),
+                );
+            });
+        });
+
+        // Resting transform of each posed model, captured when the scene loads.
+        let rest_transforms = use_ref(&cx, || RefCell::new(HashMap::new()));
+        // Joints in the rig that the model doesn't have, warned about once each.
+        let missing_joints = use_ref(&cx, || RefCell::new(HashSet::new()));
+
+        use_world(
+            &cx,
+            move |models_query: Query<&VoxelSceneModels>,
+                  mut transform_query: Query<&mut Transform>| {
+                let Ok(models) = models_query.get(entity) else {
+                    return;
+                };
+
+                let mut rest_transforms = rest_transforms.borrow_mut();
+                for (name, pose) in cx.me().rig.pose(&cx.me().joint_rotations) {
+                    let Some(entity) = models.entities.get(name) else {
+                        if missing_joints.borrow_mut().insert(name.to_owned()) {
+                            warn!(
+                                "Model `{}` has no joint named `{name}`, skipping it",
+                                cx.me().model
+                            );
+                        }
+                        continue;
+                    };
+                    let mut transform = transform_query.get_mut(*entity).unwrap();
+
+                    let rest = *rest_transforms.entry(name.to_owned()).or_insert(*transform);
+                    *transform = Transform::from_matrix(Mat4::from(pose) * rest.compute_matrix());
+                }
+            },
+        );
+
+        // Copies of this model's materials, so a flash only tints this character, with the
+        // emissive color each copy started with.
+        let materials = use_ref(&cx, || RefCell::new(Vec::new()));
+        let last_flash = use_ref(&cx, || Cell::new(0.));
+
+        use_world(
+            &cx,
+            move |models_query: Query<&VoxelSceneModels>,
+                  children_query: Query<&Children>,
+                  mut material_query: Query<&mut MeshMaterial3d<StandardMaterial>>,
+                  mut material_assets: ResMut<Assets<StandardMaterial>>| {
+                let Ok(models) = models_query.get(entity) else {
+                    return;
+                };
+
+                let mut materials = materials.borrow_mut();
+                if materials.is_empty() {
+                    for model in models.entities.values() {
+                        for entity in [*model]
+                            .into_iter()
+                            .chain(children_query.iter_descendants(*model))
+                        {
+                            let Ok(mut material) = material_query.get_mut(entity) else {
+                                continue;
+                            };
+                            let Some(copy) = material_assets.get(&material.0).cloned() else {
+                                continue;
+                            };
+
+                            let emissive = copy.emissive;
+                            material.0 = material_assets.add(copy);
+                            materials.push((material.0.clone(), emissive));
+                        }
+                    }
+                }
+
+                let flash = cx.me().flash;
+                if materials.is_empty() || last_flash.get() == flash {
+                    return;
+                }
+                last_flash.set(flash);
+
+                // The flash is added on top of any glow the material already has.
+                for (handle, emissive) in materials.iter() {
+                    if let Some(material) = material_assets.get_mut(handle) {
+                        material.emissive = *emissive + FLASH_COLOR * flash;
+                    }
+                }
+            },
+        );
+
+        (
+            StatusBar {
+                health: cx.me().health,
+                max_health: cx.me().max_health,
+                shield: cx.me().shield,
+                energy: cx.me().energy,
+                max_energy: cx.me().max_energy,
+                effects: cx.me().effects.clone(),
+                pos: *pos,
+                is_selected: cx.me().is_selected,
+            },
+            compose::from_iter((*floating).clone(), move |floating| {
+                let (text, age, line) = *floating;
+                FloatingText {
+                    text,
+                    age,
+                    pos: *pos - Vec2::new(0., 6. + line as f32 * 2.5),
+                }
+            }),
+        )
+    }
+}
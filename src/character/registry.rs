@@ -0,0 +1,64 @@
+use super::CharacterState;
+use std::{
+    collections::BTreeMap,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+/// Stable identifier for a character, unique for the lifetime of the app.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CharacterId(u32);
+
+impl CharacterId {
+    /// Create an ID that no other character has.
+    pub fn unique() -> Self {
+        static NEXT: AtomicU32 = AtomicU32::new(0);
+
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl From<CharacterId> for u64 {
+    fn from(id: CharacterId) -> Self {
+        id.0.into()
+    }
+}
+
+/// State of every character in a battle, by [`CharacterId`].
+///
+/// Characters are ordered by when their ID was created, so iteration order doesn't depend on
+/// the order characters are mounted in.
+#[derive(Clone, Default)]
+pub struct Characters {
+    states: BTreeMap<CharacterId, CharacterState>,
+}
+
+impl Characters {
+    pub fn get(&self, id: CharacterId) -> Option<&CharacterState> {
+        self.states.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: CharacterId) -> Option<&mut CharacterState> {
+        self.states.get_mut(&id)
+    }
+
+    pub fn insert(&mut self, id: CharacterId, state: CharacterState) {
+        self.states.insert(id, state);
+    }
+
+    /// Remove every character that `f` returns `false` for.
+    pub fn retain(&mut self, mut f: impl FnMut(CharacterId, &CharacterState) -> bool) {
+        self.states.retain(|id, state| f(*id, state));
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = CharacterId> + '_ {
+        self.states.keys().copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (CharacterId, &CharacterState)> {
+        self.states.iter().map(|(id, state)| (*id, state))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (CharacterId, &mut CharacterState)> {
+        self.states.iter_mut().map(|(id, state)| (*id, state))
+    }
+}
@@ -0,0 +1,103 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Joint of a [`Rig`] that rotates one model in a voxel scene.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(from = "JointDef")]
+pub struct Joint {
+    /// Name of the model in the .vox scene.
+    pub name: String,
+    /// Point the model rotates around.
+    pub pivot: Vec3,
+    /// Axis the model rotates around.
+    pub axis: Vec3,
+    /// Joint this joint is attached to, if any.
+    ///
+    /// A joint follows every rotation of its parent.
+    pub parent: Option<String>,
+}
+
+impl Joint {
+    /// Create a joint with no parent that rotates around the X axis.
+    pub fn new(name: impl Into<String>, pivot: Vec3) -> Self {
+        Self {
+            name: name.into(),
+            pivot,
+            axis: Vec3::X,
+            parent: None,
+        }
+    }
+}
+
+/// [`Joint`] as written in a definition file.
+#[derive(Deserialize)]
+struct JointDef {
+    name: String,
+    pivot: [f32; 3],
+    #[serde(default = "x_axis")]
+    axis: [f32; 3],
+    #[serde(default)]
+    parent: Option<String>,
+}
+
+fn x_axis() -> [f32; 3] {
+    Vec3::X.to_array()
+}
+
+impl From<JointDef> for Joint {
+    fn from(def: JointDef) -> Self {
+        Self {
+            name: def.name,
+            pivot: Vec3::from_array(def.pivot),
+            axis: Vec3::from_array(def.axis),
+            parent: def.parent,
+        }
+    }
+}
+
+/// Skeleton of a character model.
+///
+/// Joints are listed with parents before their children.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct Rig {
+    pub joints: Vec<Joint>,
+}
+
+impl Rig {
+    /// Rig for `character.vox`, with two arms and two legs.
+    pub fn humanoid() -> Self {
+        Self {
+            joints: vec![
+                Joint::new("left_arm", Vec3::new(0., 24., 4.)),
+                Joint::new("right_arm", Vec3::new(0., 24., 4.)),
+                Joint::new("left_leg", Vec3::new(0., 3., 5.)),
+                Joint::new("right_leg", Vec3::new(0., 3., 5.)),
+            ],
+        }
+    }
+
+    /// Compute the transform of each joint from its rotation and its parents' rotations.
+    ///
+    /// Each transform rotates a model from its resting position.
+    pub fn pose<'a>(&'a self, rotations: &[f32]) -> HashMap<&'a str, Affine3A> {
+        let mut pose = HashMap::new();
+
+        for (joint, rotation) in self.joints.iter().zip(rotations) {
+            let local = Affine3A::from_translation(joint.pivot)
+                * Affine3A::from_axis_angle(joint.axis.normalize(), *rotation)
+                * Affine3A::from_translation(-joint.pivot);
+
+            let parent = joint
+                .parent
+                .as_deref()
+                .and_then(|parent| pose.get(parent))
+                .copied()
+                .unwrap_or(Affine3A::IDENTITY);
+
+            pose.insert(joint.name.as_str(), parent * local);
+        }
+
+        pose
+    }
+}
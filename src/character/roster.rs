@@ -0,0 +1,20 @@
+use super::Team;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+/// Characters in a battle, loaded from a `.roster.ron` file.
+///
+/// Members are laid out in the order they're listed, with each team in its own row.
+#[derive(Asset, TypePath, Clone, Debug, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct Roster {
+    pub members: Vec<RosterMember>,
+}
+
+/// Character in a [`Roster`] and the team they fight for.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct RosterMember {
+    /// Path to the member's `.character.ron` definition.
+    pub character: String,
+    pub team: Team,
+}
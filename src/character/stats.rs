@@ -0,0 +1,100 @@
+use serde::Deserialize;
+
+/// Stat of a character.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub enum Stat {
+    MaxHealth,
+    MaxEnergy,
+    /// Energy regained at the start of each turn.
+    EnergyRegen,
+    /// Strength of this character's skills.
+    Attack,
+    /// Reduction of physical damage taken.
+    Defense,
+    Speed,
+    /// Reduction of elemental damage taken.
+    Resistance,
+}
+
+/// Stats of a character before any modifiers.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct BaseStats {
+    pub max_health: u32,
+    pub max_energy: u32,
+    pub energy_regen: u32,
+    pub attack: u32,
+    pub defense: u32,
+    pub speed: u32,
+    pub resistance: u32,
+}
+
+impl BaseStats {
+    pub fn get(&self, stat: Stat) -> u32 {
+        match stat {
+            Stat::MaxHealth => self.max_health,
+            Stat::MaxEnergy => self.max_energy,
+            Stat::EnergyRegen => self.energy_regen,
+            Stat::Attack => self.attack,
+            Stat::Defense => self.defense,
+            Stat::Speed => self.speed,
+            Stat::Resistance => self.resistance,
+        }
+    }
+}
+
+/// Change to a stat from a buff or a piece of equipment.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum Modifier {
+    /// Add to a stat.
+    Add(Stat, i32),
+    /// Multiply a stat, after every addition.
+    Multiply(Stat, f32),
+}
+
+/// Base stats of a character with the modifiers applied to them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StatBlock {
+    pub base: BaseStats,
+    pub modifiers: Vec<Modifier>,
+}
+
+impl StatBlock {
+    pub fn new(base: BaseStats, modifiers: Vec<Modifier>) -> Self {
+        Self { base, modifiers }
+    }
+
+    /// Get the value of `stat` after every modifier.
+    ///
+    /// Additions are summed before multipliers are applied, and the result never drops below zero.
+    pub fn get(&self, stat: Stat) -> u32 {
+        self.get_with(stat, [])
+    }
+
+    /// Get the value of `stat` after every modifier, including temporary `extra` modifiers.
+    pub fn get_with(&self, stat: Stat, extra: impl IntoIterator<Item = Modifier>) -> u32 {
+        let mut add = 0i64;
+        let mut multiply = 1.;
+
+        for modifier in self.modifiers.iter().copied().chain(extra) {
+            match modifier {
+                Modifier::Add(modified, amount) if modified == stat => add += i64::from(amount),
+                Modifier::Multiply(modified, factor) if modified == stat => multiply *= factor,
+                _ => {}
+            }
+        }
+
+        let value = (i64::from(self.base.get(stat)) + add).max(0) as f32 * multiply;
+        value.round().max(0.) as u32
+    }
+}
+
+/// Scale the `power` of a skill by its caster's `attack` against the target's `defense`.
+///
+/// Damage is reduced by half when `defense` matches `attack`.
+pub fn scale_damage(power: u32, attack: u32, defense: u32) -> u32 {
+    if attack == 0 {
+        return 0;
+    }
+
+    (u64::from(power) * u64::from(attack) / (u64::from(attack) + u64::from(defense))) as u32
+}
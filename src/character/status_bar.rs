@@ -0,0 +1,336 @@
+use super::StatusEffect;
+use actuate::{compose, prelude::*};
+use bevy::prelude::*;
+use std::cell::Cell;
+
+/// Health in each segment of a health bar.
+const SEGMENT: u32 = 25;
+
+/// Seconds recently lost health is held before it drains.
+const TRAIL_DELAY: f32 = 0.4;
+
+/// Fraction of max health drained from the recent damage segment each second.
+const TRAIL_RATE: f32 = 0.5;
+
+/// Settings for the status bars shown under characters.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct StatusBarSettings {
+    /// Show health and energy as numbers next to their bars.
+    pub show_numbers: bool,
+}
+
+impl Default for StatusBarSettings {
+    fn default() -> Self {
+        Self { show_numbers: true }
+    }
+}
+
+#[derive(Data)]
+pub struct StatusBar {
+    pub health: u32,
+    pub max_health: u32,
+    /// Damage absorbed before health.
+    pub shield: u32,
+    pub energy: u32,
+    pub max_energy: u32,
+    pub effects: Vec<StatusEffect>,
+    pub pos: Vec2,
+    pub is_selected: bool,
+}
+
+impl Compose for StatusBar {
+    fn compose(cx: Scope<Self>) -> impl Compose {
+        let font = use_world_once(&cx, |asset_server: Res<AssetServer>| {
+            asset_server.load("C&C Red Alert [INET].ttf")
+        });
+        let heart = use_world_once(&cx, |asset_server: Res<AssetServer>| {
+            asset_server.load("heart.png")
+        });
+        let energy = use_world_once(&cx, |asset_server: Res<AssetServer>| {
+            asset_server.load("energy.png")
+        });
+
+        let health_entity = use_bundle(&cx, || ());
+
+        let entity = *use_context::<Entity>(&cx).unwrap();
+        use_world_once(&cx, |mut commands: Commands| {
+            commands.entity(entity).add_child(health_entity);
+        });
+
+        let show_numbers = use_mut(&cx, || true);
+        use_world(&cx, move |settings: Res<StatusBarSettings>| {
+            SignalMut::set_if_neq(show_numbers, settings.show_numbers);
+        });
+
+        // Defeated characters are greyed out.
+        let color = if cx.me().health == 0 {
+            Color::srgb_u8(117, 117, 117)
+        } else {
+            Color::WHITE
+        };
+
+        let number = |value: u32| {
+            show_numbers.then(|| {
+                spawn((
+                    Text::new(value.to_string()),
+                    TextColor(color),
+                    TextFont {
+                        font: font.clone(),
+                        font_size: 2.,
+                        ..default()
+                    },
+                    TextLayout {
+                        justify: JustifyText::Center,
+                        ..default()
+                    },
+                    Node {
+                        margin: UiRect::left(Val::Px(0.25)),
+                        ..default()
+                    },
+                ))
+            })
+        };
+
+        let row = || Node {
+            flex_direction: FlexDirection::Row,
+            align_items: AlignItems::Center,
+            ..default()
+        };
+
+        spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(cx.me().pos.y),
+                left: Val::Px(cx.me().pos.x),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                padding: UiRect::axes(Val::Px(0.5), Val::Px(0.25)),
+                border: UiRect::all(Val::Px(0.25)),
+                row_gap: Val::Px(0.25),
+                ..default()
+            },
+            BackgroundColor(Color::BLACK),
+            BorderColor(if cx.me().is_selected {
+                color
+            } else {
+                Color::BLACK
+            }),
+        ))
+        .target(health_entity)
+        .content((
+            spawn(row()).content((
+                spawn((
+                    Node {
+                        width: Val::Px(1.),
+                        height: Val::Px(1.),
+                        margin: UiRect::right(Val::Px(0.25)),
+                        ..default()
+                    },
+                    UiImage::new(heart.clone()).with_color(color),
+                )),
+                HealthBar {
+                    health: cx.me().health,
+                    max_health: cx.me().max_health,
+                    shield: cx.me().shield,
+                },
+                number(cx.me().health),
+            )),
+            spawn(row()).content((
+                spawn((
+                    Node {
+                        width: Val::Px(1.),
+                        height: Val::Px(1.),
+                        margin: UiRect::right(Val::Px(0.25)),
+                        ..default()
+                    },
+                    UiImage::new(energy.clone()).with_color(color),
+                )),
+                EnergyPips {
+                    energy: cx.me().energy,
+                    max_energy: cx.me().max_energy,
+                },
+                number(cx.me().energy),
+                compose::from_iter(cx.me().effects.clone(), move |effect| EffectIcon {
+                    effect: *effect,
+                    color,
+                }),
+            )),
+        ))
+    }
+}
+
+/// Health bar split into segments, with the health recently lost draining behind it.
+#[derive(Data)]
+struct HealthBar {
+    health: u32,
+    max_health: u32,
+    shield: u32,
+}
+
+impl Compose for HealthBar {
+    fn compose(cx: Scope<Self>) -> impl Compose {
+        let trail = use_mut(&cx, || cx.me().health as f32);
+        let held = use_ref(&cx, || Cell::new(0.));
+        let last_health = use_ref(&cx, || Cell::new(cx.me().health));
+
+        use_world(&cx, move |time: Res<Time>| {
+            // Every hit holds the trail again, even while it's still draining.
+            if cx.me().health < last_health.replace(cx.me().health) {
+                held.set(0.);
+            }
+
+            let health = cx.me().health as f32;
+
+            // Healing fills the bar right away.
+            if *trail <= health {
+                held.set(0.);
+                SignalMut::set_if_neq(trail, health);
+                return;
+            }
+
+            if held.get() < TRAIL_DELAY {
+                held.set(held.get() + time.delta_secs());
+                return;
+            }
+
+            let drained = TRAIL_RATE * cx.me().max_health as f32 * time.delta_secs();
+            SignalMut::set(trail, (*trail - drained).max(health));
+        });
+
+        let max_health = cx.me().max_health.max(1);
+        let percent =
+            move |value: f32| Val::Percent((value / max_health as f32).clamp(0., 1.) * 100.);
+
+        let health = cx.me().health;
+        let fill = if health * 2 > max_health {
+            Color::srgb_u8(102, 187, 106)
+        } else if health * 4 > max_health {
+            Color::srgb_u8(255, 202, 40)
+        } else {
+            Color::srgb_u8(239, 83, 80)
+        };
+
+        // The shield covers the end of the health it protects.
+        let shield = cx.me().shield.min(health);
+
+        let segment = |left: Val, width: Val, color: Color| {
+            spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(0.),
+                    left,
+                    width,
+                    height: Val::Percent(100.),
+                    ..default()
+                },
+                BackgroundColor(color),
+            ))
+        };
+
+        spawn((
+            Node {
+                width: Val::Px(6.),
+                height: Val::Px(0.6),
+                ..default()
+            },
+            BackgroundColor(Color::srgb_u8(33, 33, 33)),
+        ))
+        .content((
+            segment(Val::Px(0.), percent(*trail), Color::srgb_u8(255, 205, 210)),
+            segment(Val::Px(0.), percent(health as f32), fill),
+            segment(
+                percent((health - shield) as f32),
+                percent(shield as f32),
+                Color::srgba(0.7, 0.85, 1., 0.7),
+            ),
+            compose::from_iter(
+                (1..max_health.div_ceil(SEGMENT))
+                    .map(|idx| (idx * SEGMENT) as f32)
+                    .collect::<Vec<_>>(),
+                move |divider| segment(percent(*divider), Val::Px(0.1), Color::BLACK),
+            ),
+        ))
+    }
+}
+
+/// One pip for each point of max energy, filled for each point left.
+#[derive(Data)]
+struct EnergyPips {
+    energy: u32,
+    max_energy: u32,
+}
+
+impl Compose for EnergyPips {
+    fn compose(cx: Scope<Self>) -> impl Compose {
+        let energy = cx.me().energy;
+
+        spawn(Node {
+            flex_direction: FlexDirection::Row,
+            column_gap: Val::Px(0.1),
+            ..default()
+        })
+        .content(compose::from_iter(
+            (0..cx.me().max_energy)
+                .map(|idx| idx < energy)
+                .collect::<Vec<_>>(),
+            |is_full| {
+                spawn((
+                    Node {
+                        width: Val::Px(0.4),
+                        height: Val::Px(0.6),
+                        ..default()
+                    },
+                    BackgroundColor(if *is_full {
+                        Color::srgb_u8(255, 235, 59)
+                    } else {
+                        Color::srgb_u8(66, 66, 66)
+                    }),
+                ))
+            },
+        ))
+    }
+}
+
+#[derive(Data)]
+struct EffectIcon {
+    effect: StatusEffect,
+    color: Color,
+}
+
+impl Compose for EffectIcon {
+    fn compose(cx: Scope<Self>) -> impl Compose {
+        // Icons are reused as effects come and go, so the image is loaded on every change.
+        let asset_server =
+            use_world_once(&cx, |asset_server: Res<AssetServer>| asset_server.clone());
+        let icon = asset_server.load(cx.me().effect.kind.icon());
+        let font = use_world_once(&cx, |asset_server: Res<AssetServer>| {
+            asset_server.load("C&C Red Alert [INET].ttf")
+        });
+
+        (
+            spawn((
+                Node {
+                    width: Val::Px(1.),
+                    height: Val::Px(1.),
+                    margin: UiRect::left(Val::Px(0.5)),
+                    ..default()
+                },
+                UiImage::new(icon).with_color(cx.me().color),
+            )),
+            // Show the stack count once an effect has stacked.
+            if cx.me().effect.stacks > 1 {
+                Some(spawn((
+                    Text::new(cx.me().effect.stacks.to_string()),
+                    TextColor(cx.me().color),
+                    TextFont {
+                        font: font.clone(),
+                        font_size: 1.,
+                        ..default()
+                    },
+                )))
+            } else {
+                None
+            },
+        )
+    }
+}
@@ -0,0 +1,32 @@
+use serde::Deserialize;
+
+/// Side a character fights for in a battle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub enum Team {
+    /// The player's party.
+    Player,
+    Enemy,
+    /// Characters that don't fight for either side, but can still be targeted.
+    Neutral,
+}
+
+impl Team {
+    /// Get how a character on this team sees a character on `other`.
+    pub fn allegiance(self, other: Team) -> Allegiance {
+        if self == other {
+            Allegiance::Ally
+        } else if self == Self::Neutral || other == Self::Neutral {
+            Allegiance::Neutral
+        } else {
+            Allegiance::Hostile
+        }
+    }
+}
+
+/// How one character sees another, based on their [`Team`]s.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Allegiance {
+    Ally,
+    Hostile,
+    Neutral,
+}
@@ -0,0 +1,900 @@
+//! Headless harness for running compositions in tests.
+
+use crate::{
+    character::{
+        BaseStats, CharacterAnimation, CharacterId, CharacterState, Characters, EffectKind, Rig,
+        StatBlock, Team,
+    },
+    input::{Actions, Bindings, Rebinding},
+    queue::{
+        use_queue_provider, use_queued, use_queued_with, use_queued_with_abort,
+        use_queued_with_options, use_skip, Lane, Priority, QueueHandle, QueueTrace, Skip,
+        SkippableController, TaskOptions, TaskStatus, ABORT_TIMEOUT,
+    },
+    Ui,
+};
+use actuate::prelude::*;
+use bevy::{
+    input::InputPlugin,
+    picking::{
+        backend::HitData,
+        pointer::{Location, PointerId},
+    },
+    prelude::*,
+    render::camera::NormalizedRenderTarget,
+    time::TimeUpdateStrategy,
+};
+use futures::{future, FutureExt};
+use std::{
+    future::IntoFuture,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::Notify;
+
+/// Time advanced by each call to [`Harness::step`].
+pub const FRAME: Duration = Duration::from_millis(16);
+
+/// App running a composition without a window or renderer.
+pub struct Harness {
+    app: App,
+}
+
+impl Harness {
+    pub fn new(content: impl Compose + Send + Sync + 'static) -> Self {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            InputPlugin,
+            ActuatePlugin,
+        ))
+        .init_asset::<Image>()
+        .init_asset::<Font>()
+        .init_resource::<Actions>()
+        .init_resource::<Bindings>()
+        .init_resource::<Rebinding>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME))
+        .insert_resource(UiScale(20.));
+
+        app.world_mut().spawn(Composition::new(content));
+
+        Self { app }
+    }
+
+    pub fn world(&mut self) -> &mut World {
+        self.app.world_mut()
+    }
+
+    /// Run a single frame.
+    pub fn step(&mut self) {
+        self.app.update();
+    }
+
+    /// Run frames until `duration` has passed.
+    pub fn step_for(&mut self, duration: Duration) {
+        for _ in 0..duration.as_millis().div_ceil(FRAME.as_millis()) {
+            self.step();
+        }
+    }
+
+    /// Find the first entity displaying `text`.
+    pub fn find_text(&mut self, text: &str) -> Entity {
+        let mut query = self.world().query::<(Entity, &Text)>();
+        query
+            .iter(self.world())
+            .find(|(_, t)| t.0 == text)
+            .map(|(entity, _)| entity)
+            .unwrap_or_else(|| panic!("No text `{text}` found"))
+    }
+
+    /// Find every entity named `name`.
+    pub fn find_all_named(&mut self, name: &str) -> Vec<Entity> {
+        let mut query = self.world().query::<(Entity, &Name)>();
+        query
+            .iter(self.world())
+            .filter(|(_, n)| n.as_str() == name)
+            .map(|(entity, _)| entity)
+            .collect()
+    }
+
+    /// Find the first entity named `name`.
+    pub fn find_named(&mut self, name: &str) -> Entity {
+        let mut query = self.world().query::<(Entity, &Name)>();
+        query
+            .iter(self.world())
+            .find(|(_, n)| n.as_str() == name)
+            .map(|(entity, _)| entity)
+            .unwrap_or_else(|| panic!("No entity named `{name}` found"))
+    }
+
+    /// Trigger a synthetic primary click on `entity`.
+    pub fn click(&mut self, entity: Entity) {
+        let event = Pointer::new(
+            PointerId::Mouse,
+            Location {
+                target: NormalizedRenderTarget::Image(Handle::default()),
+                position: Vec2::ZERO,
+            },
+            entity,
+            Click {
+                button: PointerButton::Primary,
+                hit: HitData::new(entity, 0., None, None),
+                duration: Duration::ZERO,
+            },
+        );
+        self.world().trigger_targets(event, entity);
+    }
+}
+
+/// Health and energy of each character in a [`Battle`], copied out every frame.
+#[derive(Resource, Default)]
+struct Stats(Vec<(u32, u32)>);
+
+/// Status effects and the turns they have left on each character in a [`Battle`], copied out
+/// every frame.
+#[derive(Resource, Default)]
+struct Effects(Vec<Vec<(EffectKind, u32)>>);
+
+/// Character without a model, for battles in tests.
+#[derive(Data)]
+struct Dummy<'a> {
+    home: Vec3,
+    team: Team,
+    /// Energy this dummy starts with, out of 10.
+    energy: u32,
+    on_mount: Box<dyn Fn(CharacterState) + 'a>,
+}
+
+impl Compose for Dummy<'_> {
+    fn compose(cx: Scope<Self>) -> impl Compose {
+        let translation = use_animated(&cx, || cx.me().home);
+        let rotation = use_animated(&cx, || Vec3::ZERO);
+        let flash = use_animated(&cx, || 0.);
+        let rig = Rig::humanoid();
+        let joints: Vec<_> = rig
+            .joints
+            .iter()
+            .map(|_| use_animated(&cx, || 0.))
+            .collect();
+
+        let skip = use_skip(&cx);
+
+        let state = use_ref(&cx, || {
+            let animation = CharacterAnimation {
+                translation: translation.controller(),
+                rotation: rotation.controller(),
+                flash: flash.controller(),
+                joints: rig
+                    .joints
+                    .iter()
+                    .zip(&joints)
+                    .map(|(joint, animated)| (joint.name.clone(), animated.controller()))
+                    .collect(),
+            };
+            let state = CharacterState {
+                energy: cx.me().energy,
+                ..CharacterState::new(
+                    String::from("Dummy"),
+                    cx.me().team,
+                    cx.me().home,
+                    StatBlock::new(
+                        BaseStats {
+                            max_health: 100,
+                            max_energy: 10,
+                            energy_regen: 2,
+                            attack: 10,
+                            defense: 0,
+                            speed: 10,
+                            resistance: 0,
+                        },
+                        Vec::new(),
+                    ),
+                    Arc::new(
+                        ron::de::from_str(include_str!("../assets/animations.clips.ron")).unwrap(),
+                    ),
+                    animation,
+                    skip,
+                )
+            };
+            (cx.me().on_mount)(state.clone());
+            state
+        });
+
+        // Skills move relative to where characters were last drawn.
+        state.translation.set_rendered(*translation);
+        state.rotation.set_rendered(*rotation);
+    }
+}
+
+/// Two dummies and the battle UI, with the player targeting the other dummy.
+#[derive(Data)]
+struct Battle {
+    /// Team the targeted dummy is on.
+    target_team: Team,
+    /// Energy the player starts with, out of 10.
+    energy: u32,
+    /// Number of Ice Shard slots on the player's skill bar.
+    ice_shards: usize,
+}
+
+impl Compose for Battle {
+    fn compose(cx: Scope<Self>) -> impl Compose {
+        let entity = *use_world_once(&cx, |mut commands: Commands| {
+            commands.spawn(Node::default()).id()
+        });
+        use_provider(&cx, || entity);
+
+        use_queue_provider(&cx);
+
+        let character_states = use_mut(&cx, Characters::default);
+        let [player, target] = *use_ref(&cx, || [(); 2].map(|_| CharacterId::unique()));
+
+        use_world(
+            &cx,
+            move |mut stats: ResMut<Stats>, effects: Option<ResMut<Effects>>| {
+                stats.0 = character_states
+                    .iter()
+                    .map(|(_, state)| (state.health, state.energy))
+                    .collect();
+
+                if let Some(mut effects) = effects {
+                    effects.0 = character_states
+                        .iter()
+                        .map(|(_, state)| {
+                            state
+                                .effects
+                                .iter()
+                                .map(|effect| (effect.kind, effect.turns))
+                                .collect()
+                        })
+                        .collect();
+                }
+            },
+        );
+
+        // The target mounts first, so the registry can't rely on mount order.
+        (
+            Dummy {
+                home: Vec3::new(0., 0., -40.),
+                team: cx.me().target_team,
+                energy: 10,
+                on_mount: Box::new(move |state| {
+                    SignalMut::update(character_states, move |states| states.insert(target, state));
+                }),
+            },
+            Dummy {
+                home: Vec3::new(0., 0., 40.),
+                team: Team::Player,
+                energy: cx.me().energy,
+                on_mount: Box::new(move |state| {
+                    SignalMut::update(character_states, move |states| states.insert(player, state));
+                }),
+            },
+            Ui {
+                character_states,
+                player,
+                target: Some(target),
+                skills: vec![String::from("ice_shard"); cx.me().ice_shards],
+            },
+        )
+    }
+}
+
+/// Records the order its tasks run in.
+#[derive(Data, Default)]
+struct PriorityOrder;
+
+impl Compose for PriorityOrder {
+    fn compose(cx: Scope<Self>) -> impl Compose {
+        let order = use_world_once(&cx, |order: Res<Order>| order.0.clone());
+
+        let tasks = [Priority::Low, Priority::Normal, Priority::High].map(|priority| {
+            use_queued_with_options(
+                &cx,
+                TaskOptions::default().with_priority(priority),
+                move || async move { order.lock().unwrap().push(priority) },
+            )
+        });
+
+        use_ref(&cx, || {
+            for task in &tasks {
+                task.queue();
+            }
+        });
+    }
+}
+
+#[derive(Resource, Default)]
+struct Order(Arc<Mutex<Vec<Priority>>>);
+
+/// What the tasks in a test did, in order.
+#[derive(Resource, Clone, Default)]
+struct Log(Arc<Mutex<Vec<String>>>);
+
+impl Log {
+    fn push(&self, entry: impl Into<String>) {
+        self.0.lock().unwrap().push(entry.into());
+    }
+
+    fn contains(&self, entry: &str) -> bool {
+        self.0.lock().unwrap().iter().any(|logged| logged == entry)
+    }
+}
+
+/// Signal that lets every task waiting on it finish.
+#[derive(Resource, Clone, Default)]
+struct Release(Arc<Notify>);
+
+impl Release {
+    async fn wait(&self) {
+        self.0.notified().await;
+    }
+}
+
+/// Tasks for each actor, then a barrier, then a high priority task for an actor, that each wait
+/// for a [`Release`].
+#[derive(Data, Default)]
+struct Lanes;
+
+impl Compose for Lanes {
+    fn compose(cx: Scope<Self>) -> impl Compose {
+        let (log, release) = use_world_once(&cx, |log: Res<Log>, release: Res<Release>| {
+            (log.clone(), release.clone())
+        });
+
+        let tasks = [
+            ("first", Lane::Actor(0), Priority::Normal),
+            ("second", Lane::Actor(0), Priority::Normal),
+            ("other", Lane::Actor(1), Priority::Normal),
+            ("barrier", Lane::Barrier, Priority::Normal),
+            ("late", Lane::Actor(1), Priority::High),
+        ]
+        .map(|(name, lane, priority)| {
+            use_queued_with_options(
+                &cx,
+                TaskOptions::default()
+                    .with_lane(lane)
+                    .with_priority(priority),
+                move || async move {
+                    log.push(format!("start {name}"));
+                    release.wait().await;
+                    log.push(format!("end {name}"));
+                },
+            )
+        });
+
+        use_ref(&cx, || {
+            for task in &tasks {
+                task.queue();
+            }
+        });
+    }
+}
+
+/// Queues a task that waits for a [`Release`], and awaits its handle.
+#[derive(Data, Default)]
+struct AwaitHandle;
+
+impl Compose for AwaitHandle {
+    fn compose(cx: Scope<Self>) -> impl Compose {
+        let (log, release) = use_world_once(&cx, |log: Res<Log>, release: Res<Release>| {
+            (log.clone(), release.clone())
+        });
+
+        let task = use_queued(&cx, move || async move {
+            release.wait().await;
+            log.push("task finished");
+        });
+
+        use_local_task(&cx, move || async move {
+            let status = task.queue().await;
+            log.push(format!("awaited {status:?}"));
+        });
+    }
+}
+
+/// Queues an attack on the target behind a task that waits for a [`Release`], then changes
+/// the target before the attack runs.
+#[derive(Data, Default)]
+struct CaptureTarget;
+
+impl Compose for CaptureTarget {
+    fn compose(cx: Scope<Self>) -> impl Compose {
+        let (log, release) = use_world_once(&cx, |log: Res<Log>, release: Res<Release>| {
+            (log.clone(), release.clone())
+        });
+
+        let target = use_mut(&cx, || 1);
+
+        let blocker = use_queued(&cx, move || async move { release.wait().await });
+        let attack = use_queued_with(&cx, move |captured: u32| async move {
+            log.push(format!("hit {captured} while targeting {}", *target));
+        });
+
+        use_ref(&cx, || {
+            blocker.queue();
+            attack.queue(*target);
+            SignalMut::set(target, 2);
+        });
+    }
+}
+
+/// Queues a task that never finishes, with a short timeout, followed by another task.
+#[derive(Data, Default)]
+struct Stuck;
+
+impl Compose for Stuck {
+    fn compose(cx: Scope<Self>) -> impl Compose {
+        let log = use_world_once(&cx, |log: Res<Log>| log.clone());
+
+        let stuck = use_queued_with_abort(
+            &cx,
+            TaskOptions::default().with_timeout(STUCK_TIMEOUT),
+            |()| future::pending::<()>(),
+            move |()| async move { log.push("aborted") },
+        );
+        let next = use_queued(&cx, move || async move { log.push("next") });
+
+        use_ref(&cx, || {
+            stuck.queue(());
+            next.queue();
+        });
+    }
+}
+
+/// Queues a task that never finishes and never finishes aborting, with a short timeout,
+/// followed by another task.
+#[derive(Data, Default)]
+struct StuckAbort;
+
+impl Compose for StuckAbort {
+    fn compose(cx: Scope<Self>) -> impl Compose {
+        let log = use_world_once(&cx, |log: Res<Log>| log.clone());
+
+        let stuck = use_queued_with_abort(
+            &cx,
+            TaskOptions::default().with_timeout(STUCK_TIMEOUT),
+            |()| future::pending::<()>(),
+            move |()| async move {
+                log.push("aborting");
+                future::pending::<()>().await
+            },
+        );
+        let next = use_queued(&cx, move || async move { log.push("next") });
+
+        use_ref(&cx, || {
+            stuck.queue(());
+            next.queue();
+        });
+    }
+}
+
+/// Timeout of the tasks queued by [`Stuck`] and [`StuckAbort`].
+const STUCK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Queues a slow animation, recording its value in [`Animated`] and handing out the queue's
+/// skip switch as [`Skipper`].
+#[derive(Data, Default)]
+struct SlowAnimation;
+
+impl Compose for SlowAnimation {
+    fn compose(cx: Scope<Self>) -> impl Compose {
+        let log = use_world_once(&cx, |log: Res<Log>| log.clone());
+
+        let skip = use_skip(&cx);
+        use_world_once(&cx, |mut commands: Commands| {
+            commands.insert_resource(Skipper(skip.clone()))
+        });
+
+        let animated = use_animated(&cx, || 0.);
+        let controller = use_ref(&cx, || {
+            SkippableController::new(animated.controller(), skip.clone(), 0.)
+        });
+        controller.set_rendered(*animated);
+
+        let task = use_queued(&cx, move || async move {
+            controller.animate(100., Duration::from_secs(10)).await;
+            log.push("done");
+        });
+        use_ref(&cx, || {
+            task.queue();
+        });
+
+        use_world(&cx, move |mut animated: ResMut<Animated>| {
+            animated.0 = controller.get();
+        });
+    }
+}
+
+/// Skip switch of the queue in a test.
+#[derive(Resource)]
+struct Skipper(Skip);
+
+/// Latest value of the animation in [`SlowAnimation`].
+#[derive(Resource, Default)]
+struct Animated(f32);
+
+/// Once released, queues a labeled task that waits for another [`Release`], and a task that's
+/// cancelled before it runs.
+#[derive(Data, Default)]
+struct Traced;
+
+impl Compose for Traced {
+    fn compose(cx: Scope<Self>) -> impl Compose {
+        let release = use_world_once(&cx, |release: Res<Release>| release.clone());
+
+        let first = use_queued_with_options(
+            &cx,
+            TaskOptions::default().with_label("first"),
+            move || async move { release.wait().await },
+        );
+        let withdrawn = use_queued_with_options(
+            &cx,
+            TaskOptions::default().with_label("withdrawn"),
+            || async {},
+        );
+
+        use_local_task(&cx, move || async move {
+            release.wait().await;
+            first.queue();
+            withdrawn.queue().cancel();
+        });
+    }
+}
+
+/// Queues a task that never finishes followed by another, handing out their handles as
+/// [`Handles`].
+#[derive(Data, Default)]
+struct Abandoned;
+
+impl Compose for Abandoned {
+    fn compose(cx: Scope<Self>) -> impl Compose {
+        let handles = use_world_once(&cx, |handles: Res<Handles>| handles.clone());
+
+        let stuck = use_queued(&cx, future::pending::<()>);
+        let next = use_queued(&cx, || async {});
+
+        use_ref(&cx, || {
+            handles
+                .0
+                .lock()
+                .unwrap()
+                .extend([stuck.queue(), next.queue()]);
+        });
+    }
+}
+
+/// Handles of the tasks queued by [`Abandoned`].
+#[derive(Resource, Clone, Default)]
+struct Handles(Arc<Mutex<Vec<QueueHandle>>>);
+
+/// Whether a [`Mountable`] composes its content.
+#[derive(Resource)]
+struct Mounted(bool);
+
+/// Composes its `content` while the [`Mounted`] resource is `true`.
+#[derive(Data)]
+struct Mountable<C> {
+    content: C,
+}
+
+impl<C: Compose> Compose for Mountable<C> {
+    fn compose(cx: Scope<Self>) -> impl Compose {
+        let is_mounted = use_mut(&cx, || true);
+        use_world(&cx, move |mounted: Res<Mounted>| {
+            SignalMut::set_if_neq(is_mounted, mounted.0)
+        });
+
+        (*is_mounted).then(|| Signal::map(cx.me(), |me| &me.content))
+    }
+}
+
+/// Runs a test's `content` below a queue provider.
+#[derive(Data)]
+struct QueueRoot<C> {
+    content: C,
+}
+
+impl<C: Compose> Compose for QueueRoot<C> {
+    fn compose(cx: Scope<Self>) -> impl Compose {
+        use_queue_provider(&cx);
+
+        Signal::map(cx.me(), |me| &me.content)
+    }
+}
+
+/// Start a queue test running `content`.
+fn queue_harness(content: impl Compose + Send + Sync + 'static) -> Harness {
+    let mut harness = Harness::new(QueueRoot { content });
+    harness.world().init_resource::<Log>();
+    harness.world().init_resource::<Release>();
+    harness
+}
+
+#[test]
+fn it_runs_higher_priority_tasks_first() {
+    let mut harness = queue_harness(PriorityOrder);
+    harness.world().init_resource::<Order>();
+    harness.step_for(Duration::from_millis(100));
+
+    let order = harness
+        .world()
+        .resource::<Order>()
+        .0
+        .lock()
+        .unwrap()
+        .clone();
+
+    assert_eq!(order, [Priority::High, Priority::Normal, Priority::Low]);
+}
+
+#[test]
+fn it_ends_the_turn() {
+    let mut harness = Harness::new(Battle {
+        target_team: Team::Enemy,
+        energy: 10,
+        ice_shards: 1,
+    });
+    harness.world().init_resource::<Stats>();
+    harness.world().init_resource::<Effects>();
+    harness.step();
+
+    let ice_shard = harness.find_named("Ice Shard");
+    harness.click(ice_shard);
+    harness.step_for(Duration::from_secs(5));
+
+    let end_turn = harness.find_text("End Turn");
+    harness.click(end_turn);
+    harness.step_for(Duration::from_millis(100));
+
+    // The player regenerates the energy Ice Shard cost, and the target's chill ticks down.
+    assert_eq!(harness.world().resource::<Stats>().0, [(100, 9), (50, 10)]);
+    assert_eq!(
+        harness.world().resource::<Effects>().0,
+        [vec![], vec![(EffectKind::Chilled, 1)]]
+    );
+}
+
+#[test]
+fn it_casts_ice_shard_at_the_target() {
+    let mut harness = Harness::new(Battle {
+        target_team: Team::Enemy,
+        energy: 10,
+        ice_shards: 1,
+    });
+    harness.world().init_resource::<Stats>();
+    harness.step();
+
+    let ice_shard = harness.find_named("Ice Shard");
+    harness.click(ice_shard);
+    harness.step_for(Duration::from_secs(5));
+
+    assert_eq!(harness.world().resource::<Stats>().0, [(100, 7), (50, 10)]);
+}
+
+#[test]
+fn it_skips_a_queued_cast_the_caster_can_no_longer_pay_for() {
+    let mut harness = Harness::new(Battle {
+        target_team: Team::Enemy,
+        energy: 4,
+        ice_shards: 2,
+    });
+    harness.world().init_resource::<Stats>();
+    harness.step();
+
+    // Both casts are affordable when they're queued, but only the first once it has run.
+    for ice_shard in harness.find_all_named("Ice Shard") {
+        harness.click(ice_shard);
+    }
+    harness.step_for(Duration::from_secs(10));
+
+    assert_eq!(harness.world().resource::<Stats>().0, [(100, 1), (50, 10)]);
+}
+
+#[test]
+fn it_does_not_cast_ice_shard_at_allies() {
+    let mut harness = Harness::new(Battle {
+        target_team: Team::Player,
+        energy: 10,
+        ice_shards: 1,
+    });
+    harness.world().init_resource::<Stats>();
+    harness.step();
+
+    let ice_shard = harness.find_named("Ice Shard");
+    harness.click(ice_shard);
+    harness.step_for(Duration::from_secs(5));
+
+    assert_eq!(
+        harness.world().resource::<Stats>().0,
+        [(100, 10), (100, 10)]
+    );
+}
+
+#[test]
+fn it_runs_actor_lanes_in_parallel_before_a_barrier() {
+    let mut harness = queue_harness(Lanes);
+    harness.step_for(Duration::from_millis(100));
+
+    // Each actor runs one task at a time, and the barrier waits for both lanes.
+    let log = harness.world().resource::<Log>().clone();
+    assert!(log.contains("start first") && log.contains("start other"));
+    assert!(!log.contains("start second") && !log.contains("start barrier"));
+
+    harness.world().resource::<Release>().0.notify_waiters();
+    harness.step_for(Duration::from_millis(100));
+    assert!(log.contains("end first") && log.contains("end other"));
+    assert!(log.contains("start second") && !log.contains("start barrier"));
+
+    // Tasks queued after the barrier wait for it, even with a higher priority.
+    assert!(!log.contains("start late"));
+
+    harness.world().resource::<Release>().0.notify_waiters();
+    harness.step_for(Duration::from_millis(100));
+    assert!(log.contains("end second") && log.contains("start barrier"));
+    assert!(!log.contains("start late"));
+
+    harness.world().resource::<Release>().0.notify_waiters();
+    harness.step_for(Duration::from_millis(100));
+    assert!(log.contains("end barrier") && log.contains("start late"));
+}
+
+#[test]
+fn it_awaits_a_queued_task() {
+    let mut harness = queue_harness(AwaitHandle);
+    harness.step_for(Duration::from_millis(100));
+
+    let log = harness.world().resource::<Log>().clone();
+    assert!(!log.contains("awaited Finished"));
+
+    harness.world().resource::<Release>().0.notify_waiters();
+    harness.step_for(Duration::from_millis(100));
+    assert_eq!(
+        *log.0.lock().unwrap(),
+        ["task finished", "awaited Finished"]
+    );
+}
+
+#[test]
+fn it_captures_arguments_when_queued() {
+    let mut harness = queue_harness(CaptureTarget);
+    harness.step_for(Duration::from_millis(100));
+
+    harness.world().resource::<Release>().0.notify_waiters();
+    harness.step_for(Duration::from_millis(100));
+
+    // The attack hits the target it was queued with, not the one picked afterwards.
+    let log = harness.world().resource::<Log>().clone();
+    assert_eq!(*log.0.lock().unwrap(), ["hit 1 while targeting 2"]);
+}
+
+#[test]
+fn it_aborts_a_task_that_times_out() {
+    let mut harness = queue_harness(Stuck);
+    harness.step_for(STUCK_TIMEOUT / 2);
+
+    let log = harness.world().resource::<Log>().clone();
+    assert!(log.0.lock().unwrap().is_empty());
+
+    harness.step_for(STUCK_TIMEOUT);
+
+    // The next task waits for the stuck one to finish aborting.
+    assert_eq!(*log.0.lock().unwrap(), ["aborted", "next"]);
+}
+
+#[test]
+fn it_drops_an_abort_handler_that_times_out() {
+    let mut harness = queue_harness(StuckAbort);
+    harness.step_for(STUCK_TIMEOUT + Duration::from_millis(100));
+
+    let log = harness.world().resource::<Log>().clone();
+    assert_eq!(*log.0.lock().unwrap(), ["aborting"]);
+
+    harness.step_for(ABORT_TIMEOUT);
+
+    // The next task runs once the stuck abort handler is dropped.
+    assert_eq!(*log.0.lock().unwrap(), ["aborting", "next"]);
+}
+
+#[test]
+fn it_skips_animations_to_their_end() {
+    let mut harness = queue_harness(SlowAnimation);
+    harness.world().init_resource::<Animated>();
+    harness.step_for(Duration::from_millis(100));
+    assert!(harness.world().resource::<Animated>().0 < 100.);
+
+    harness.world().resource::<Skipper>().0.skip();
+    harness.step_for(Duration::from_millis(100));
+
+    let log = harness.world().resource::<Log>().clone();
+    assert!(log.contains("done"));
+    assert_eq!(harness.world().resource::<Animated>().0, 100.);
+}
+
+#[test]
+fn it_cancels_tasks_when_their_composition_is_dropped() {
+    let mut harness = queue_harness(Mountable { content: Abandoned });
+    harness.world().init_resource::<Handles>();
+    harness.world().insert_resource(Mounted(true));
+    harness.step_for(Duration::from_millis(100));
+
+    let handles = harness
+        .world()
+        .resource::<Handles>()
+        .0
+        .lock()
+        .unwrap()
+        .clone();
+    assert_eq!(
+        handles.iter().map(QueueHandle::status).collect::<Vec<_>>(),
+        [TaskStatus::Running, TaskStatus::Pending]
+    );
+
+    harness.world().insert_resource(Mounted(false));
+    harness.step_for(Duration::from_millis(100));
+
+    // Awaiting either handle no longer waits forever.
+    for handle in handles {
+        assert_eq!(
+            handle.into_future().now_or_never(),
+            Some(TaskStatus::Cancelled)
+        );
+    }
+}
+
+#[test]
+fn it_cancels_tasks_when_the_queue_is_dropped() {
+    let mut harness = Harness::new(Mountable {
+        content: QueueRoot { content: Abandoned },
+    });
+    harness.world().init_resource::<Handles>();
+    harness.world().insert_resource(Mounted(true));
+    harness.step_for(Duration::from_millis(100));
+
+    harness.world().insert_resource(Mounted(false));
+    harness.step_for(Duration::from_millis(100));
+
+    let handles = harness
+        .world()
+        .resource::<Handles>()
+        .0
+        .lock()
+        .unwrap()
+        .clone();
+    for handle in handles {
+        assert_eq!(
+            handle.into_future().now_or_never(),
+            Some(TaskStatus::Cancelled)
+        );
+    }
+}
+
+#[test]
+fn it_traces_tasks_queued_after_the_trace_is_added() {
+    let mut harness = queue_harness(Traced);
+    harness.step();
+
+    harness.world().init_resource::<QueueTrace>();
+    harness.step();
+
+    harness.world().resource::<Release>().0.notify_waiters();
+    harness.step_for(Duration::from_millis(100));
+    harness.world().resource::<Release>().0.notify_waiters();
+    harness.step_for(Duration::from_millis(100));
+
+    let entries = harness.world().resource::<QueueTrace>().entries();
+    let labels: Vec<_> = entries.iter().map(|entry| entry.label.as_str()).collect();
+    assert_eq!(labels, ["first", "withdrawn"]);
+
+    let first = &entries[0];
+    let started_at = first.started_at.unwrap();
+    let ended_at = first.ended_at.unwrap();
+    assert!(first.queued_at <= started_at && started_at <= ended_at);
+    assert!(!first.is_cancelled);
+
+    // The withdrawn task ends without ever starting.
+    let withdrawn = &entries[1];
+    assert!(withdrawn.is_cancelled);
+    assert!(withdrawn.started_at.is_none() && withdrawn.ended_at.is_some());
+}
@@ -0,0 +1,433 @@
+use bevy::{
+    input::InputSystem,
+    prelude::*,
+    reflect::{DynamicEnum, DynamicVariant, Enum},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Action the player can take from a keyboard or gamepad.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Action {
+    NextTarget,
+    PreviousTarget,
+    /// Control the next character in the player's party.
+    NextCharacter,
+    /// Use the skill in a slot, starting from 0.
+    Skill(usize),
+    EndTurn,
+    /// Fast-forward through the queued actions.
+    Skip,
+}
+
+/// Key or gamepad button bound to an [`Action`].
+///
+/// Bindings are written with the name of their key or button, like `Key("Tab")`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "BindingDef", into = "BindingDef")]
+pub enum Binding {
+    Key(KeyCode),
+    Gamepad(GamepadButton),
+}
+
+#[derive(Serialize, Deserialize)]
+enum BindingDef {
+    Key(String),
+    Gamepad(String),
+}
+
+impl From<Binding> for BindingDef {
+    fn from(binding: Binding) -> Self {
+        match binding {
+            Binding::Key(key) => Self::Key(key.variant_name().to_owned()),
+            Binding::Gamepad(button) => Self::Gamepad(button.variant_name().to_owned()),
+        }
+    }
+}
+
+impl TryFrom<BindingDef> for Binding {
+    type Error = UnknownBinding;
+
+    fn try_from(def: BindingDef) -> Result<Self, Self::Error> {
+        match def {
+            BindingDef::Key(name) => from_variant_name(&name)
+                .map(Self::Key)
+                .ok_or(UnknownBinding(name)),
+            BindingDef::Gamepad(name) => from_variant_name(&name)
+                .map(Self::Gamepad)
+                .ok_or(UnknownBinding(name)),
+        }
+    }
+}
+
+/// Parse a unit variant of a reflected enum from its name.
+fn from_variant_name<T: FromReflect>(name: &str) -> Option<T> {
+    T::from_reflect(&DynamicEnum::new(name, DynamicVariant::Unit))
+}
+
+/// Error for a binding to a key or button that doesn't exist.
+#[derive(Debug)]
+pub struct UnknownBinding(String);
+
+impl fmt::Display for UnknownBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown key or button `{}`", self.0)
+    }
+}
+
+/// Keys and buttons bound to each [`Action`].
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Bindings {
+    actions: BTreeMap<Action, Vec<Binding>>,
+    /// Config file changes are saved to, if these bindings were loaded from one.
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let digits = [
+            KeyCode::Digit1,
+            KeyCode::Digit2,
+            KeyCode::Digit3,
+            KeyCode::Digit4,
+        ];
+        let face_buttons = [
+            GamepadButton::South,
+            GamepadButton::East,
+            GamepadButton::West,
+            GamepadButton::North,
+        ];
+
+        let mut actions = BTreeMap::from([
+            (
+                Action::NextTarget,
+                vec![
+                    Binding::Key(KeyCode::Tab),
+                    Binding::Gamepad(GamepadButton::RightTrigger),
+                ],
+            ),
+            (
+                Action::PreviousTarget,
+                vec![Binding::Gamepad(GamepadButton::LeftTrigger)],
+            ),
+            (
+                Action::NextCharacter,
+                vec![
+                    Binding::Key(KeyCode::KeyQ),
+                    Binding::Gamepad(GamepadButton::RightTrigger2),
+                ],
+            ),
+            (
+                Action::EndTurn,
+                vec![
+                    Binding::Key(KeyCode::Enter),
+                    Binding::Gamepad(GamepadButton::Start),
+                ],
+            ),
+            (
+                Action::Skip,
+                vec![
+                    Binding::Key(KeyCode::Space),
+                    Binding::Gamepad(GamepadButton::Select),
+                ],
+            ),
+        ]);
+
+        for (slot, (key, button)) in digits.into_iter().zip(face_buttons).enumerate() {
+            actions.insert(
+                Action::Skill(slot),
+                vec![Binding::Key(key), Binding::Gamepad(button)],
+            );
+        }
+
+        Self {
+            actions,
+            path: None,
+        }
+    }
+}
+
+impl Bindings {
+    /// Load bindings from the config file at `path`, writing the defaults there if it doesn't
+    /// exist yet.
+    ///
+    /// Changes made with [`Bindings::bind`] and [`Bindings::unbind`] are saved back to `path`.
+    pub fn load_or_create(path: &Path) -> Self {
+        let bindings: Self = match fs::read_to_string(path) {
+            Ok(config) => ron::de::from_str(&config).unwrap_or_else(|error| {
+                warn!(
+                    "Invalid bindings in {}, using defaults: {error}",
+                    path.display()
+                );
+                Self::default()
+            }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                let bindings = Self::default();
+                if let Err(error) = bindings.save(path) {
+                    warn!("Failed to write bindings to {}: {error}", path.display());
+                }
+                bindings
+            }
+            Err(error) => {
+                warn!("Failed to read bindings from {}: {error}", path.display());
+                Self::default()
+            }
+        };
+
+        Self {
+            path: Some(path.to_owned()),
+            ..bindings
+        }
+    }
+
+    /// Get the keys and buttons bound to `action`.
+    pub fn get(&self, action: Action) -> impl Iterator<Item = Binding> + '_ {
+        self.actions.get(&action).into_iter().flatten().copied()
+    }
+
+    /// Get the action `binding` is bound to, if any.
+    pub fn action(&self, binding: Binding) -> Option<Action> {
+        self.actions
+            .iter()
+            .find(|(_, bindings)| bindings.contains(&binding))
+            .map(|(action, _)| *action)
+    }
+
+    /// Bind `binding` to `action`, moving it off of any other action, and save the change.
+    pub fn bind(&mut self, action: Action, binding: Binding) {
+        if self.get(action).any(|bound| bound == binding) {
+            return;
+        }
+
+        self.remove(binding);
+        self.actions.entry(action).or_default().push(binding);
+        self.save_changes();
+    }
+
+    /// Remove `binding` from every action, and save the change.
+    pub fn unbind(&mut self, binding: Binding) {
+        if self.remove(binding) {
+            self.save_changes();
+        }
+    }
+
+    /// Remove `binding` from every action, returning `true` if it was bound to any.
+    fn remove(&mut self, binding: Binding) -> bool {
+        let mut is_removed = false;
+        for bindings in self.actions.values_mut() {
+            let len = bindings.len();
+            bindings.retain(|bound| *bound != binding);
+            is_removed |= bindings.len() != len;
+        }
+        is_removed
+    }
+
+    /// Save these bindings to the config file they were loaded from, if any.
+    fn save_changes(&self) {
+        if let Some(path) = &self.path {
+            if let Err(error) = self.save(path) {
+                warn!("Failed to write bindings to {}: {error}", path.display());
+            }
+        }
+    }
+
+    /// Save these bindings to the config file at `path`.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let config = ron::ser::to_string_pretty(self, Default::default())
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        fs::write(path, config)
+    }
+}
+
+/// Actions the player started this frame.
+#[derive(Resource, Default)]
+pub struct Actions {
+    just_pressed: HashSet<Action>,
+}
+
+impl Actions {
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+}
+
+/// Action waiting to be bound to the next key the player presses.
+///
+/// Keys don't trigger [`Actions`] while an action is waiting.
+#[derive(Resource, Default)]
+pub struct Rebinding {
+    action: Option<Action>,
+    /// Key that was pressed and the other action it's bound to, waiting for the key to be
+    /// pressed again to move it.
+    conflict: Option<(KeyCode, Action)>,
+}
+
+impl Rebinding {
+    /// Bind the next key pressed to `action`.
+    ///
+    /// Escape cancels, and Backspace clears the keys bound to `action`.
+    pub fn start(&mut self, action: Action) {
+        self.action = Some(action);
+        self.conflict = None;
+    }
+
+    /// Get the action waiting for a key, if any.
+    pub fn action(&self) -> Option<Action> {
+        self.action
+    }
+
+    /// Get the key waiting to be pressed again to move it off of another action, with that
+    /// action.
+    pub fn conflict(&self) -> Option<(KeyCode, Action)> {
+        self.conflict
+    }
+}
+
+fn update_rebinding(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut bindings: ResMut<Bindings>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    let Some(action) = rebinding.action else {
+        return;
+    };
+    let Some(key) = keys.get_just_pressed().next().copied() else {
+        return;
+    };
+
+    match key {
+        KeyCode::Escape => {}
+        KeyCode::Backspace => {
+            let keys: Vec<_> = bindings
+                .get(action)
+                .filter(|binding| matches!(binding, Binding::Key(_)))
+                .collect();
+            for binding in keys {
+                bindings.unbind(binding);
+            }
+        }
+        _ => {
+            // Taking a key from anything but another skill needs the key pressed twice.
+            let other = bindings
+                .action(Binding::Key(key))
+                .filter(|other| *other != action && !matches!(other, Action::Skill(_)));
+            if let Some(other) = other {
+                if rebinding.conflict != Some((key, other)) {
+                    rebinding.conflict = Some((key, other));
+                    return;
+                }
+            }
+
+            bindings.bind(action, Binding::Key(key));
+        }
+    }
+
+    *rebinding = Rebinding::default();
+}
+
+fn update_actions(
+    bindings: Res<Bindings>,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    rebinding: Res<Rebinding>,
+    mut actions: ResMut<Actions>,
+) {
+    actions.just_pressed.clear();
+
+    for (action, action_bindings) in &bindings.actions {
+        let is_pressed = action_bindings.iter().any(|binding| match binding {
+            Binding::Key(key) => rebinding.action.is_none() && keys.just_pressed(*key),
+            Binding::Gamepad(button) => {
+                gamepads.iter().any(|gamepad| gamepad.just_pressed(*button))
+            }
+        });
+
+        if is_pressed {
+            actions.just_pressed.insert(*action);
+        }
+    }
+}
+
+/// Plugin to turn keyboard and gamepad input into [`Actions`].
+pub struct ActionPlugin {
+    /// Path to the bindings config file.
+    pub path: PathBuf,
+}
+
+impl Plugin for ActionPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Bindings::load_or_create(&self.path))
+            .init_resource::<Actions>()
+            .init_resource::<Rebinding>()
+            // Keys pressed to rebind an action don't trigger actions, even on the frame
+            // they're bound.
+            .add_systems(
+                PreUpdate,
+                (update_actions, update_rebinding)
+                    .chain()
+                    .after(InputSystem),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+
+    /// Press `key` for a frame and run [`update_rebinding`].
+    fn press(world: &mut World, key: KeyCode) {
+        let mut keys = world.resource_mut::<ButtonInput<KeyCode>>();
+        keys.release(key);
+        keys.clear();
+        keys.press(key);
+
+        world.run_system_once(update_rebinding).unwrap();
+    }
+
+    #[test]
+    fn it_moves_a_rebound_key_off_its_old_action() {
+        let mut bindings = Bindings::default();
+        let key = Binding::Key(KeyCode::Digit1);
+        bindings.bind(Action::Skill(1), key);
+
+        assert!(bindings.get(Action::Skill(1)).any(|binding| binding == key));
+        assert!(!bindings.get(Action::Skill(0)).any(|binding| binding == key));
+
+        bindings.unbind(key);
+        assert!(!bindings.get(Action::Skill(1)).any(|binding| binding == key));
+    }
+
+    #[test]
+    fn it_asks_before_taking_a_key_from_another_action() {
+        let mut world = World::new();
+        world.init_resource::<ButtonInput<KeyCode>>();
+        world.init_resource::<Bindings>();
+        world.init_resource::<Rebinding>();
+        world.resource_mut::<Rebinding>().start(Action::Skill(0));
+
+        let enter = Binding::Key(KeyCode::Enter);
+        press(&mut world, KeyCode::Enter);
+        assert_eq!(
+            world.resource::<Bindings>().action(enter),
+            Some(Action::EndTurn)
+        );
+        assert_eq!(
+            world.resource::<Rebinding>().conflict(),
+            Some((KeyCode::Enter, Action::EndTurn))
+        );
+
+        press(&mut world, KeyCode::Enter);
+        assert_eq!(
+            world.resource::<Bindings>().action(enter),
+            Some(Action::Skill(0))
+        );
+        assert_eq!(world.resource::<Rebinding>().action(), None);
+    }
+}
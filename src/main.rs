@@ -1,20 +1,43 @@
-use actuate::prelude::*;
+use actuate::{compose, prelude::*};
 use bevy::{core_pipeline::bloom::Bloom, prelude::*};
+use futures::future;
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashSet,
+    env,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
 
 mod character;
-use self::character::{Character, CharacterState};
+
+#[cfg(test)]
+mod harness;
+use self::character::{
+    Allegiance, Character, CharacterDef, CharacterId, CharacterPlugin, CharacterState, Characters,
+    ClipLibrary, Roster, Stat, Team,
+};
+
+mod input;
+use self::input::{Action, ActionPlugin, Actions};
 
 mod queue;
-use self::queue::{use_queue_provider, use_queued};
+use self::queue::{
+    use_queue_provider, use_queue_state, use_queued_with_options, use_skip, Priority, QueueHandle,
+    QueueTrace, TaskOptions,
+};
 
 mod skill;
 use self::skill::IceShard;
 
 #[derive(Data)]
 pub struct Ui<'a> {
-    character_states: SignalMut<'a, Vec<CharacterState>>,
-    player_idx: usize,
-    target_idx: usize,
+    character_states: SignalMut<'a, Characters>,
+    /// Character the player is controlling.
+    player: CharacterId,
+    target: Option<CharacterId>,
+    /// Names of the player's skills, in slot order.
+    skills: Vec<String>,
 }
 
 impl Compose for Ui<'_> {
@@ -27,10 +50,62 @@ impl Compose for Ui<'_> {
             asset_server.load("C&C Red Alert [INET].ttf")
         });
 
-        let is_turn_done = use_mut(&cx, || false);
-        let on_click = use_queued(&cx, move || async move {
-            SignalMut::update(turn, |turn| *turn += 1);
-            SignalMut::set(is_turn_done, false);
+        let queue = use_queue_state(&cx);
+
+        let skip = use_skip(&cx);
+
+        // Ending the turn is a low priority barrier, so it waits for any skills that are still
+        // queued.
+        let on_click = use_queued_with_options(
+            &cx,
+            TaskOptions::default()
+                .with_priority(Priority::Low)
+                .with_label("End Turn"),
+            move || async move {
+                let before = (*cx.me().character_states).clone();
+
+                // Every character still standing ends their turn and starts the next one.
+                let mut after = before.clone();
+                for (_, state) in after.iter_mut().filter(|(_, state)| !state.is_defeated()) {
+                    state.end_turn();
+                    state.start_turn();
+                }
+
+                SignalMut::set(cx.me().character_states, after.clone());
+                SignalMut::update(turn, |turn| *turn += 1);
+
+                future::join_all(
+                    after
+                        .iter()
+                        .filter(|(id, after)| {
+                            after.is_defeated()
+                                && before.get(*id).is_some_and(|before| !before.is_defeated())
+                        })
+                        .map(|(_, after)| after.collapse()),
+                )
+                .await;
+            },
+        );
+        // Skill names without a skill, warned about once each.
+        let unknown_skills = use_ref(&cx, || Mutex::new(HashSet::new()));
+
+        let end_turn_handle = use_ref(&cx, || Mutex::new(None::<QueueHandle>));
+        let end_turn = move || {
+            let mut handle = end_turn_handle.lock().unwrap();
+
+            // Ending the turn again while it's still waiting doesn't queue another.
+            if !handle.as_ref().is_some_and(QueueHandle::is_pending) {
+                *handle = Some(on_click.queue());
+            }
+        };
+
+        use_world(&cx, move |actions: Res<Actions>| {
+            if actions.just_pressed(Action::Skip) {
+                skip.skip();
+            }
+            if actions.just_pressed(Action::EndTurn) {
+                end_turn();
+            }
         });
 
         spawn((
@@ -57,6 +132,21 @@ impl Compose for Ui<'_> {
                 PickingBehavior::IGNORE,
             ))
             .content((
+                spawn((
+                    Text::new(
+                        cx.me()
+                            .character_states
+                            .get(cx.me().player)
+                            .map(|state| state.name.clone())
+                            .unwrap_or_default(),
+                    ),
+                    TextFont {
+                        font: font.clone(),
+                        font_size: 1.5,
+                        ..default()
+                    },
+                    PickingBehavior::IGNORE,
+                )),
                 spawn((
                     Node {
                         flex_direction: FlexDirection::Row,
@@ -66,25 +156,35 @@ impl Compose for Ui<'_> {
                     },
                     PickingBehavior::IGNORE,
                 ))
-                .content((
-                    IceShard {
-                        character_states: cx.me().character_states,
-                        player_idx: cx.me().player_idx,
-                        target_idx: cx.me().target_idx,
-                        turn: *turn,
-                        is_turn_done: *is_turn_done,
-                    },
-                    IceShard {
-                        character_states: cx.me().character_states,
-                        player_idx: cx.me().player_idx,
-                        target_idx: cx.me().target_idx,
-                        turn: *turn,
-                        is_turn_done: *is_turn_done,
+                .content(compose::from_iter(
+                    cx.me()
+                        .skills
+                        .iter()
+                        .cloned()
+                        .enumerate()
+                        .collect::<Vec<_>>(),
+                    move |skill| {
+                        let (slot, name) = &*skill;
+                        match name.as_str() {
+                            "ice_shard" => Some(IceShard {
+                                character_states: cx.me().character_states,
+                                player: cx.me().player,
+                                target: cx.me().target,
+                                turn: *turn,
+                                slot: *slot,
+                            }),
+                            _ => {
+                                if unknown_skills.lock().unwrap().insert(name.clone()) {
+                                    warn!("Unknown skill `{name}`, leaving slot {slot} empty");
+                                }
+                                None
+                            }
+                        }
                     },
                 )),
                 spawn((
                     Text::new("End Turn"),
-                    TextColor(if *is_turn_done {
+                    TextColor(if !queue.is_idle {
                         Color::srgb_u8(117, 117, 117)
                     } else {
                         Color::WHITE
@@ -99,10 +199,7 @@ impl Compose for Ui<'_> {
                         ..default()
                     },
                 ))
-                .observe(move |_: In<Trigger<Pointer<Click>>>| {
-                    SignalMut::set(is_turn_done, true);
-                    on_click.queue();
-                }),
+                .observe(move |_: In<Trigger<Pointer<Click>>>| end_turn()),
             )),
         )
     }
@@ -113,7 +210,7 @@ struct Game;
 
 impl Compose for Game {
     fn compose(cx: Scope<Self>) -> impl Compose {
-        let target = use_mut(&cx, || 0);
+        let target = use_mut(&cx, || None::<CharacterId>);
 
         let entity = *use_world_once(&cx, |mut commands: Commands| {
             commands.spawn(Node::default()).id()
@@ -135,65 +232,282 @@ impl Compose for Game {
             ));
         });
 
-        let character_states = use_mut(&cx, Vec::new);
+        let character_states = use_mut(&cx, Characters::default);
 
-        (
-            Character {
-                index: 0,
-                target: *target,
-                transation: Vec3::new(0., 0., 40.),
-                on_mount: Box::new(move |state| {
-                    SignalMut::update(character_states, move |states| states.push(state));
-                }),
-                on_click: Box::new(move || SignalMut::set(target, 0)),
-                health: character_states
-                    .get(0)
-                    .map(|state| state.health)
-                    .unwrap_or(100),
-                energy: character_states
-                    .get(0)
-                    .map(|state| state.energy)
-                    .unwrap_or(10),
-            },
-            Character {
-                index: 1,
-                target: *target,
-                transation: Vec3::new(0., 0., -40.),
-                on_mount: Box::new(move |state| {
-                    SignalMut::update(character_states, move |states| states.push(state));
-                }),
-                on_click: Box::new(move || SignalMut::set(target, 1)),
-                health: character_states
-                    .get(1)
-                    .map(|state| state.health)
-                    .unwrap_or(100),
-                energy: character_states
-                    .get(1)
-                    .map(|state| state.energy)
-                    .unwrap_or(10),
+        let roster_handle = use_world_once(&cx, |asset_server: Res<AssetServer>| {
+            asset_server.load::<Roster>("battle.roster.ron")
+        });
+        let clips_handle = use_world_once(&cx, |asset_server: Res<AssetServer>| {
+            asset_server.load::<ClipLibrary>("animations.clips.ron")
+        });
+        // Definition of each roster member, requested once the roster loads.
+        let def_handles = use_ref(&cx, || {
+            RefCell::new(None::<Vec<(Team, Handle<CharacterDef>)>>)
+        });
+        // Characters in the battle, in roster order.
+        let roster = use_mut(&cx, Vec::<Combatant>::new);
+        let clips = use_mut(&cx, || Arc::new(ClipLibrary::default()));
+        let is_loaded = use_ref(&cx, || Cell::new(false));
+
+        use_world(
+            &cx,
+            move |asset_server: Res<AssetServer>,
+                  rosters: Res<Assets<Roster>>,
+                  defs: Res<Assets<CharacterDef>>,
+                  clip_libraries: Res<Assets<ClipLibrary>>| {
+                if is_loaded.get() {
+                    return;
+                }
+                let Some(roster_asset) = rosters.get(roster_handle) else {
+                    return;
+                };
+
+                let mut def_handles = def_handles.borrow_mut();
+                let def_handles = def_handles.get_or_insert_with(|| {
+                    roster_asset
+                        .members
+                        .iter()
+                        .map(|member| (member.team, asset_server.load(member.character.clone())))
+                        .collect()
+                });
+
+                let Some(clip_library) = clip_libraries.get(clips_handle) else {
+                    return;
+                };
+                let Some(loaded) = def_handles
+                    .iter()
+                    .map(|(team, handle)| Some((*team, defs.get(handle)?.clone())))
+                    .collect::<Option<Vec<_>>>()
+                else {
+                    return;
+                };
+                is_loaded.set(true);
+
+                SignalMut::set(clips, Arc::new(clip_library.clone()));
+                SignalMut::set(
+                    roster,
+                    loaded
+                        .into_iter()
+                        .map(|(team, def)| Combatant {
+                            id: CharacterId::unique(),
+                            team,
+                            def,
+                        })
+                        .collect(),
+                );
             },
-            Ui {
+        );
+
+        // Forget the state of characters that have left the roster.
+        use_world(&cx, move || {
+            let ids: Vec<_> = roster.iter().map(|combatant| combatant.id).collect();
+            if character_states.ids().all(|id| ids.contains(&id)) {
+                return;
+            }
+
+            SignalMut::update(character_states, move |states| {
+                states.retain(|id, _| ids.contains(&id))
+            });
+        });
+
+        // Member of the player's party they switched to, if any.
+        let active = use_mut(&cx, || None::<CharacterId>);
+
+        let party: Vec<_> = roster
+            .iter()
+            .filter(|combatant| combatant.team == Team::Player)
+            .cloned()
+            .collect();
+        let is_standing = |id| {
+            character_states
+                .get(id)
+                .is_none_or(|state| !state.is_defeated())
+        };
+
+        // The player controls the party member they switched to, or the first one still
+        // standing.
+        let player = party
+            .iter()
+            .find(|combatant| Some(combatant.id) == *active && is_standing(combatant.id))
+            .or_else(|| party.iter().find(|combatant| is_standing(combatant.id)))
+            .or(party.first())
+            .cloned();
+        let player_id = player.as_ref().map(|player| player.id);
+
+        // Switch to the next party member still standing with the keyboard or gamepad.
+        let standing: Vec<_> = party
+            .iter()
+            .map(|combatant| combatant.id)
+            .filter(|&id| is_standing(id))
+            .collect();
+        use_world(&cx, move |actions: Res<Actions>| {
+            if !actions.just_pressed(Action::NextCharacter) {
+                return;
+            }
+
+            let current = player_id.and_then(|id| standing.iter().position(|&other| other == id));
+            let next = current.map_or(0, |idx| (idx + 1) % standing.len());
+            if let Some(&id) = standing.get(next) {
+                SignalMut::set(active, Some(id));
+            }
+        });
+
+        // Cycle the target through the player's enemies with the keyboard or gamepad, and move
+        // it off of characters as they're defeated.
+        use_world(&cx, move |actions: Res<Actions>| {
+            let ids: Vec<_> = character_states.ids().collect();
+            let count = ids.len();
+            let Some(player) = player_id.and_then(|id| character_states.get(id)) else {
+                return;
+            };
+
+            // Characters that left the battle can't stay targeted.
+            let current = target.and_then(|target| ids.iter().position(|&id| id == target));
+
+            // Stepping by `count - 1` wraps around to the previous character.
+            let step = if actions.just_pressed(Action::NextTarget) {
+                1
+            } else if actions.just_pressed(Action::PreviousTarget) {
+                count - 1
+            } else if current.is_none_or(|idx| {
+                character_states
+                    .get(ids[idx])
+                    .is_some_and(CharacterState::is_defeated)
+            }) {
+                1
+            } else {
+                return;
+            };
+
+            let start = current.unwrap_or(count - 1);
+            let next = (1..=count)
+                .map(|offset| ids[(start + offset * step) % count])
+                .find(|&id| {
+                    character_states.get(id).is_some_and(|state| {
+                        !state.is_defeated() && player.allegiance(state) == Allegiance::Hostile
+                    })
+                });
+            if next.is_some() || current.is_none() {
+                SignalMut::set_if_neq(target, next);
+            }
+        });
+
+        (
+            compose::from_iter((*roster).clone(), move |combatant| {
+                let Combatant { id, team, def } = &*combatant;
+                let id = *id;
+                let stats = def.stat_block();
+
+                // Each team stands in its own row.
+                let teammates: Vec<_> = roster
+                    .iter()
+                    .filter(|other| other.team == *team)
+                    .map(|other| other.id)
+                    .collect();
+                let slot = teammates.iter().position(|&other| other == id).unwrap();
+
+                Character {
+                    id,
+                    target: *target,
+                    transation: home_slot(*team, slot, teammates.len()),
+                    name: def.name.clone(),
+                    team: *team,
+                    model: def.model.clone(),
+                    rig: def.rig.clone(),
+                    clips: (*clips).clone(),
+                    stats: stats.clone(),
+                    on_mount: Box::new(move |state| {
+                        SignalMut::update(character_states, move |states| states.insert(id, state));
+                    }),
+                    on_click: Box::new(move || {
+                        // Defeated characters can't be targeted.
+                        if character_states
+                            .get(id)
+                            .is_some_and(|state| !state.is_defeated())
+                        {
+                            SignalMut::set(target, Some(id))
+                        }
+                    }),
+                    health: character_states
+                        .get(id)
+                        .map(|state| state.health)
+                        .unwrap_or_else(|| stats.get(Stat::MaxHealth)),
+                    energy: character_states
+                        .get(id)
+                        .map(|state| state.energy)
+                        .unwrap_or_else(|| stats.get(Stat::MaxEnergy)),
+                    shield: character_states
+                        .get(id)
+                        .map(|state| state.shield)
+                        .unwrap_or(def.shield),
+                    effects: character_states
+                        .get(id)
+                        .map(|state| state.effects.clone())
+                        .unwrap_or_default(),
+                }
+            }),
+            player.map(|player| Ui {
                 character_states,
-                player_idx: 0,
-                target_idx: *target,
-            },
+                player: player.id,
+                target: *target,
+                skills: player.def.skills,
+            }),
         )
     }
 }
 
+/// Character taking part in a battle.
+#[derive(Clone)]
+struct Combatant {
+    id: CharacterId,
+    team: Team,
+    def: CharacterDef,
+}
+
+/// Home position of the character in `slot` of the `count` characters on `team`.
+///
+/// The player's party and their enemies face each other in rows, with neutrals off to the side.
+fn home_slot(team: Team, slot: usize, count: usize) -> Vec3 {
+    let offset = (slot as f32 - (count - 1) as f32 / 2.) * 30.;
+    match team {
+        Team::Player => Vec3::new(offset, 0., 40.),
+        Team::Enemy => Vec3::new(offset, 0., -40.),
+        Team::Neutral => Vec3::new(-90., 0., offset),
+    }
+}
+
 fn setup(mut commands: Commands) {
     commands.spawn(Composition::new(Game));
 }
 
 fn main() {
-    App::new()
-        .add_plugins((
-            DefaultPlugins.set(ImagePlugin::default_nearest()),
-            MeshPickingPlugin,
-            ActuatePlugin,
-            voxy::DefaultPlugins,
-        ))
-        .add_systems(Startup, setup)
-        .insert_resource(UiScale(20.))
-        .run();
+    let mut app = App::new();
+    app.add_plugins((
+        DefaultPlugins.set(ImagePlugin::default_nearest()),
+        MeshPickingPlugin,
+        ActuatePlugin,
+        voxy::DefaultPlugins,
+        ActionPlugin {
+            path: PathBuf::from("bindings.ron"),
+        },
+        CharacterPlugin,
+    ))
+    .add_systems(Startup, setup)
+    .insert_resource(UiScale(20.));
+
+    // Set `QUEUE_TRACE` to a file path to write a trace of every queued task on exit.
+    if let Ok(path) = env::var("QUEUE_TRACE") {
+        app.insert_resource(QueueTrace::default()).add_systems(
+            Last,
+            move |mut exit_events: EventReader<AppExit>, trace: Res<QueueTrace>| {
+                if exit_events.read().next().is_some() {
+                    if let Err(error) = trace.write_to(&path) {
+                        error!("Failed to write queue trace to {path}: {error}");
+                    }
+                }
+            },
+        );
+    }
+
+    app.run();
 }
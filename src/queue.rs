@@ -1,59 +1,991 @@
-use actuate::prelude::*;
-use futures::channel::oneshot;
-use std::{cell::Cell, future::Future};
-use tokio::sync::mpsc;
+use actuate::{animation::AnimationController, prelude::*};
+use bevy::prelude::*;
+use futures::{
+    channel::oneshot,
+    future::{self, Either},
+};
+use std::{
+    cell::Cell,
+    cmp::Ordering,
+    collections::BinaryHeap,
+    fs::File,
+    future::{Future, IntoFuture},
+    io::{self, BufWriter, Write},
+    mem,
+    path::Path,
+    pin::{pin, Pin},
+    sync::{
+        atomic::{AtomicBool, Ordering as AtomicOrdering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::{mpsc, Notify};
+
+/// Default time a queued task can run before it's aborted.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Time a timed out task's abort handler can run before it's dropped too.
+pub const ABORT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Priority of a queued task.
+///
+/// The queue always runs the highest priority task that is waiting next,
+/// and tasks with the same priority run in the order they were queued.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Critical,
+}
+
+/// Lane a queued task runs in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Lane {
+    /// Wait for every lane to drain, then run alone.
+    ///
+    /// Tasks queued after a barrier wait for it to finish, even if they have a higher priority.
+    #[default]
+    Barrier,
+    /// Run in order with the other tasks for the actor with this key,
+    /// alongside tasks for other actors.
+    Actor(u64),
+}
+
+/// Options for tasks queued from [`use_queued_with_options`].
+#[derive(Clone, Debug, Default)]
+pub struct TaskOptions {
+    pub priority: Priority,
+    pub label: Option<String>,
+    pub lane: Lane,
+    /// How long this task can run before it's aborted,
+    /// or [`DEFAULT_TIMEOUT`] if `None`.
+    pub timeout: Option<Duration>,
+}
+
+impl TaskOptions {
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn with_lane(mut self, lane: Lane) -> Self {
+        self.lane = lane;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+/// Snapshot of the queue's state, updated as tasks are queued and run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueueState {
+    /// Number of tasks waiting to run.
+    pub pending: usize,
+    /// Labels of the running tasks.
+    pub running_labels: Vec<String>,
+    /// `true` if no task is running.
+    pub is_idle: bool,
+}
+
+impl Default for QueueState {
+    fn default() -> Self {
+        Self {
+            pending: 0,
+            running_labels: Vec::new(),
+            is_idle: true,
+        }
+    }
+}
+
+/// Status of a queued task.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TaskStatus {
+    Pending,
+    Running,
+    Finished,
+    Cancelled,
+}
+
+struct HandleState {
+    status: Mutex<TaskStatus>,
+    abort: Notify,
+    /// Notified when the abort handler has run for too long.
+    kill: Notify,
+    done: Notify,
+    queue_tx: mpsc::UnboundedSender<Message>,
+}
+
+/// Handle to a task returned from [`UseQueued::queue`].
+///
+/// Awaiting this handle waits for the task to finish or be cancelled,
+/// and returns its final [`TaskStatus`].
+#[derive(Clone)]
+pub struct QueueHandle {
+    state: Arc<HandleState>,
+}
+
+impl QueueHandle {
+    fn new(queue_tx: mpsc::UnboundedSender<Message>) -> Self {
+        Self {
+            state: Arc::new(HandleState {
+                status: Mutex::new(TaskStatus::Pending),
+                abort: Notify::new(),
+                kill: Notify::new(),
+                done: Notify::new(),
+                queue_tx,
+            }),
+        }
+    }
+
+    pub fn status(&self) -> TaskStatus {
+        *self.state.status.lock().unwrap()
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.status() == TaskStatus::Pending
+    }
+
+    /// Cancel this task.
+    ///
+    /// A pending task is removed from the queue without running.
+    /// A running task has its future dropped and its abort handler run
+    /// before the next task starts.
+    pub fn cancel(&self) {
+        let mut status = self.state.status.lock().unwrap();
+        match *status {
+            TaskStatus::Pending => {
+                *status = TaskStatus::Cancelled;
+                self.state.done.notify_waiters();
+
+                // If the queue was already dropped there's nothing left to notify.
+                let _ = self.state.queue_tx.send(Message::Cancelled);
+            }
+            TaskStatus::Running => self.state.abort.notify_one(),
+            TaskStatus::Finished | TaskStatus::Cancelled => {}
+        }
+    }
+
+    fn start(&self) -> bool {
+        let mut status = self.state.status.lock().unwrap();
+        if *status == TaskStatus::Pending {
+            *status = TaskStatus::Running;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn finish(&self, status: TaskStatus) {
+        *self.state.status.lock().unwrap() = status;
+        self.state.done.notify_waiters();
+    }
+
+    /// Drop this task's abort handler without waiting for it to finish.
+    fn kill(&self) {
+        self.state.kill.notify_one();
+    }
+}
+
+impl IntoFuture for QueueHandle {
+    type Output = TaskStatus;
+
+    type IntoFuture = Pin<Box<dyn Future<Output = TaskStatus> + Send>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(async move {
+            loop {
+                // Create the notification before checking the status
+                // so finishing in between isn't missed.
+                let done = self.state.done.notified();
+
+                match self.status() {
+                    TaskStatus::Pending | TaskStatus::Running => done.await,
+                    status @ (TaskStatus::Finished | TaskStatus::Cancelled) => return status,
+                }
+            }
+        })
+    }
+}
+
+fn describe(label: Option<&str>) -> &str {
+    label.unwrap_or("unlabeled task")
+}
+
+type TaskSender<T> = mpsc::UnboundedSender<(oneshot::Sender<()>, QueueHandle, T)>;
+
+struct Task {
+    options: TaskOptions,
+    handle: QueueHandle,
+    queued_at: Instant,
+    /// Send this task to the composition that queued it,
+    /// returning `false` if that composition was dropped.
+    start: Box<dyn FnOnce(oneshot::Sender<()>) -> bool + Send>,
+}
+
+enum Message {
+    Queued(Task),
+    Cancelled,
+    Tick,
+}
+
+struct Entry {
+    seq: u64,
+    task: Task,
+    record: Option<TraceRecord>,
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.task
+            .options
+            .priority
+            .cmp(&other.task.options.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+struct Running {
+    lane: Lane,
+    label: Option<String>,
+    handle: QueueHandle,
+    /// Time this task started, from the app's [`Time`].
+    started: Duration,
+    timeout: Duration,
+    /// Time this task timed out and was told to abort, if it has.
+    aborted_at: Option<Duration>,
+    done_rx: oneshot::Receiver<()>,
+    record: Option<TraceRecord>,
+}
+
+struct Scheduler {
+    rx: mpsc::UnboundedReceiver<Message>,
+    pending: BinaryHeap<Entry>,
+    running: Vec<Running>,
+    seq: u64,
+    /// Trace new tasks are recorded in, if the app has one.
+    trace: Arc<Mutex<Option<QueueTrace>>>,
+    /// Time elapsed in the app, updated every frame.
+    clock: Arc<Mutex<Duration>>,
+}
+
+impl Scheduler {
+    fn new(
+        rx: mpsc::UnboundedReceiver<Message>,
+        trace: Arc<Mutex<Option<QueueTrace>>>,
+        clock: Arc<Mutex<Duration>>,
+    ) -> Self {
+        Self {
+            rx,
+            pending: BinaryHeap::new(),
+            running: Vec::new(),
+            seq: 0,
+            trace,
+            clock,
+        }
+    }
+
+    fn now(&self) -> Duration {
+        *self.clock.lock().unwrap()
+    }
+
+    fn handle(&mut self, message: Message) {
+        match message {
+            Message::Queued(task) => self.push(task),
+            Message::Cancelled => {}
+            Message::Tick => self.check_timeouts(),
+        }
+    }
+
+    fn push(&mut self, task: Task) {
+        let record = self
+            .trace
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|trace| trace.record(describe(task.options.label.as_deref()), task.queued_at));
+
+        self.pending.push(Entry {
+            seq: self.seq,
+            task,
+            record,
+        });
+        self.seq += 1;
+    }
+
+    /// Start every pending task that's allowed to run.
+    ///
+    /// Tasks in an actor's lane run one at a time, alongside tasks in other lanes.
+    /// A barrier waits for every lane to drain and then runs alone.
+    /// Tasks queued after a barrier wait for it whatever their priority,
+    /// and it goes ahead of lower priority tasks queued before it.
+    fn start_ready(&mut self) {
+        self.pending.retain(|entry| {
+            if entry.task.handle.status() == TaskStatus::Cancelled {
+                if let Some(record) = &entry.record {
+                    record.end(true);
+                }
+                return false;
+            }
+            true
+        });
+
+        loop {
+            let first_barrier = self
+                .pending
+                .iter()
+                .filter(|entry| entry.task.options.lane == Lane::Barrier)
+                .map(|entry| entry.seq)
+                .min();
+
+            let mut is_blocked = self
+                .running
+                .iter()
+                .any(|running| running.lane == Lane::Barrier);
+            let mut is_barrier_dropped = false;
+
+            // Visit tasks from the highest to lowest priority.
+            let mut entries = mem::take(&mut self.pending).into_sorted_vec();
+            entries.reverse();
+
+            for entry in entries {
+                let is_held = first_barrier.is_some_and(|seq| entry.seq > seq);
+                if !is_blocked && !is_held {
+                    match entry.task.options.lane {
+                        Lane::Barrier => {
+                            if self.running.is_empty() {
+                                is_blocked = self.start(entry);
+                                is_barrier_dropped = !is_blocked;
+                                continue;
+                            }
+                            is_blocked = true;
+                        }
+                        Lane::Actor(actor) => {
+                            let is_busy = self
+                                .running
+                                .iter()
+                                .any(|running| running.lane == Lane::Actor(actor));
+                            if !is_busy {
+                                self.start(entry);
+                                continue;
+                            }
+                        }
+                    }
+                }
+
+                self.pending.push(entry);
+            }
+
+            // Tasks held back by a barrier that was dropped can start now.
+            if !is_barrier_dropped {
+                break;
+            }
+        }
+    }
+
+    fn start(&mut self, entry: Entry) -> bool {
+        let Entry { task, record, .. } = entry;
+        let Task {
+            options,
+            handle,
+            start,
+            ..
+        } = task;
+
+        let (done_tx, done_rx) = oneshot::channel();
+        if !start(done_tx) {
+            // The composition that queued this task was removed before it could run.
+            warn!(
+                "Skipping `{}`: its composition was dropped",
+                describe(options.label.as_deref())
+            );
+            handle.finish(TaskStatus::Cancelled);
+            if let Some(record) = record {
+                record.end(true);
+            }
+            return false;
+        }
+
+        if let Some(record) = &record {
+            record.start();
+        }
+
+        self.running.push(Running {
+            lane: options.lane,
+            label: options.label,
+            handle,
+            started: self.now(),
+            timeout: options.timeout.unwrap_or(DEFAULT_TIMEOUT),
+            aborted_at: None,
+            done_rx,
+            record,
+        });
+        true
+    }
+
+    /// Abort any running tasks that have timed out.
+    ///
+    /// Aborted tasks keep their lane busy until their abort handler finishes,
+    /// so the next task in the lane can't animate the same controllers at the same time.
+    /// An abort handler that runs for longer than [`ABORT_TIMEOUT`] is dropped as well,
+    /// freeing the lane.
+    fn check_timeouts(&mut self) {
+        let now = self.now();
+
+        self.running.retain_mut(|running| match running.aborted_at {
+            None if now.saturating_sub(running.started) >= running.timeout => {
+                warn!(
+                    "`{}` timed out after {:?}, skipping it",
+                    describe(running.label.as_deref()),
+                    running.timeout
+                );
+
+                // Drop the stuck future so its composition can run its next task.
+                running.handle.cancel();
+                running.aborted_at = Some(now);
+                true
+            }
+            Some(aborted_at) if now.saturating_sub(aborted_at) >= ABORT_TIMEOUT => {
+                warn!(
+                    "Aborting `{}` took longer than {:?}, dropping it",
+                    describe(running.label.as_deref()),
+                    ABORT_TIMEOUT
+                );
+
+                running.handle.kill();
+                running.handle.finish(TaskStatus::Cancelled);
+                if let Some(record) = &running.record {
+                    record.end(true);
+                }
+                false
+            }
+            _ => true,
+        });
+    }
+
+    fn finish(&mut self, idx: usize, result: Result<(), oneshot::Canceled>) {
+        let running = self.running.remove(idx);
+        if result.is_err() {
+            warn!(
+                "`{}` was dropped while running",
+                describe(running.label.as_deref())
+            );
+
+            // Its composition was removed, so nothing else will finish its handle.
+            running.handle.finish(TaskStatus::Cancelled);
+        }
+
+        if let Some(record) = &running.record {
+            record.end(result.is_err() || running.handle.status() == TaskStatus::Cancelled);
+        }
+    }
+
+    fn snapshot(&self) -> QueueState {
+        QueueState {
+            pending: self.pending.len(),
+            running_labels: self
+                .running
+                .iter()
+                .map(|running| describe(running.label.as_deref()).to_owned())
+                .collect(),
+            is_idle: self.running.is_empty(),
+        }
+    }
+}
+
+impl Drop for Scheduler {
+    fn drop(&mut self) {
+        // The queue provider was removed, so these tasks will never run or report back.
+        self.rx.close();
+        while let Ok(message) = self.rx.try_recv() {
+            if let Message::Queued(task) = message {
+                task.handle.finish(TaskStatus::Cancelled);
+            }
+        }
+
+        for entry in self.pending.drain() {
+            entry.task.handle.finish(TaskStatus::Cancelled);
+            if let Some(record) = &entry.record {
+                record.end(true);
+            }
+        }
+
+        for running in self.running.drain(..) {
+            if running.handle.status() == TaskStatus::Running {
+                running.handle.finish(TaskStatus::Cancelled);
+            }
+            if let Some(record) = &running.record {
+                record.end(true);
+            }
+        }
+    }
+}
+
+/// Recorded run of a queued task.
+///
+/// Times are measured from when the [`QueueTrace`] was created.
+#[derive(Clone, Debug)]
+pub struct TraceEntry {
+    pub label: String,
+    pub queued_at: Duration,
+    pub started_at: Option<Duration>,
+    pub ended_at: Option<Duration>,
+    pub is_cancelled: bool,
+}
+
+/// Trace of every task run by the queue.
+///
+/// Tasks queued while this resource is in the app are recorded.
+#[derive(Resource, Clone)]
+pub struct QueueTrace {
+    start: Instant,
+    entries: Arc<Mutex<Vec<TraceEntry>>>,
+}
+
+impl Default for QueueTrace {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            entries: Arc::default(),
+        }
+    }
+}
+
+impl QueueTrace {
+    pub fn entries(&self) -> Vec<TraceEntry> {
+        self.entries.lock().unwrap().clone()
+    }
+
+    /// Write this trace to `path` as tab-separated values, with times in milliseconds.
+    pub fn write_to(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(file, "label\tqueued\tstarted\tended\tcancelled")?;
+
+        let millis = |time: Option<Duration>| {
+            time.map(|time| time.as_millis().to_string())
+                .unwrap_or_else(|| String::from("-"))
+        };
+        for entry in self.entries.lock().unwrap().iter() {
+            writeln!(
+                file,
+                "{}\t{}\t{}\t{}\t{}",
+                entry.label,
+                entry.queued_at.as_millis(),
+                millis(entry.started_at),
+                millis(entry.ended_at),
+                entry.is_cancelled
+            )?;
+        }
+
+        file.flush()
+    }
+
+    fn record(&self, label: &str, queued_at: Instant) -> TraceRecord {
+        let mut entries = self.entries.lock().unwrap();
+        entries.push(TraceEntry {
+            label: label.to_owned(),
+            queued_at: queued_at.saturating_duration_since(self.start),
+            started_at: None,
+            ended_at: None,
+            is_cancelled: false,
+        });
+
+        TraceRecord {
+            trace: self.clone(),
+            idx: entries.len() - 1,
+        }
+    }
+}
+
+struct TraceRecord {
+    trace: QueueTrace,
+    idx: usize,
+}
+
+impl TraceRecord {
+    fn start(&self) {
+        let now = self.trace.start.elapsed();
+        self.trace.entries.lock().unwrap()[self.idx].started_at = Some(now);
+    }
+
+    fn end(&self, is_cancelled: bool) {
+        let now = self.trace.start.elapsed();
+        let entry = &mut self.trace.entries.lock().unwrap()[self.idx];
+        entry.ended_at = Some(now);
+        entry.is_cancelled = is_cancelled;
+    }
+}
+
+struct SkipState {
+    is_skipping: AtomicBool,
+    notify: Notify,
+}
+
+/// Fast-forward switch for the queue.
+///
+/// While skipping, every [`SkippableController`] jumps to the end of its animations
+/// until the queue runs out of tasks.
+#[derive(Clone)]
+pub struct Skip {
+    state: Arc<SkipState>,
+    queue_state: Arc<Mutex<QueueState>>,
+}
+
+impl Skip {
+    /// Resolve the running and pending tasks instantly.
+    pub fn skip(&self) {
+        if self.queue_state.lock().unwrap().is_idle {
+            return;
+        }
+
+        self.state.is_skipping.store(true, AtomicOrdering::SeqCst);
+        self.state.notify.notify_waiters();
+    }
+
+    pub fn is_skipping(&self) -> bool {
+        self.state.is_skipping.load(AtomicOrdering::SeqCst)
+    }
+
+    fn reset(&self) {
+        self.state.is_skipping.store(false, AtomicOrdering::SeqCst);
+    }
+
+    async fn wait(&self) {
+        loop {
+            let notified = self.state.notify.notified();
+            if self.is_skipping() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Animation controller that jumps to its end value while the queue is skipping.
+#[derive(Clone)]
+pub struct SkippableController<T> {
+    controller: AnimationController<T>,
+    skip: Skip,
+    rendered: Arc<Mutex<T>>,
+}
+
+impl<T> SkippableController<T> {
+    /// Create a controller, starting at `value`.
+    pub fn new(controller: AnimationController<T>, skip: Skip, value: T) -> Self {
+        Self {
+            controller,
+            skip,
+            rendered: Arc::new(Mutex::new(value)),
+        }
+    }
+
+    /// Get the value this controller's animation was last rendered with.
+    pub fn get(&self) -> T
+    where
+        T: Clone,
+    {
+        self.rendered.lock().unwrap().clone()
+    }
+
+    /// Record the value this controller's animation was rendered with.
+    pub fn set_rendered(&self, value: T) {
+        *self.rendered.lock().unwrap() = value;
+    }
+
+    async fn run<F: Future>(&self, animate: impl Fn(Duration) -> F, duration: Duration) {
+        if self.skip.is_skipping() {
+            animate(Duration::ZERO).await;
+            return;
+        }
+
+        let is_skipped = {
+            let animation = pin!(animate(duration));
+            let skip = pin!(self.skip.wait());
+            matches!(future::select(animation, skip).await, Either::Right(_))
+        };
+
+        if is_skipped {
+            animate(Duration::ZERO).await;
+        }
+    }
+}
+
+impl SkippableController<f32> {
+    pub async fn animate(&self, to: f32, duration: Duration) {
+        self.run(|duration| self.controller.animate(to, duration), duration)
+            .await
+    }
+}
+
+impl SkippableController<Vec3> {
+    pub async fn animate(&self, to: Vec3, duration: Duration) {
+        self.run(|duration| self.controller.animate(to, duration), duration)
+            .await
+    }
+}
 
 struct Queue {
-    tx: mpsc::UnboundedSender<mpsc::UnboundedSender<oneshot::Sender<()>>>,
+    tx: mpsc::UnboundedSender<Message>,
+    state: Arc<Mutex<QueueState>>,
+    skip: Skip,
 }
 
 pub fn use_queue_provider(cx: ScopeState) {
-    let (tx, rx) = use_ref(cx, || {
-        let (tx, rx) = mpsc::unbounded_channel::<mpsc::UnboundedSender<oneshot::Sender<()>>>();
-        (tx, Cell::new(Some(rx)))
+    let state = use_mut(cx, QueueState::default);
+
+    let (tx, rx, shared_state, skip, clock) = use_ref(cx, || {
+        let (tx, rx) = mpsc::unbounded_channel::<Message>();
+        let shared_state = Arc::new(Mutex::default());
+        let skip = Skip {
+            state: Arc::new(SkipState {
+                is_skipping: AtomicBool::new(false),
+                notify: Notify::new(),
+            }),
+            queue_state: shared_state.clone(),
+        };
+        (
+            tx,
+            Cell::new(Some(rx)),
+            shared_state,
+            skip,
+            Arc::new(Mutex::new(Duration::ZERO)),
+        )
+    });
+
+    // Tasks are traced while a `QueueTrace` resource is in the app.
+    let initial_trace = use_world_once(cx, |trace: Option<Res<QueueTrace>>| {
+        trace.map(|trace| trace.clone())
     });
+    let trace = use_ref(cx, || Arc::new(Mutex::new(initial_trace.clone())));
 
     use_local_task(cx, move || async move {
-        let mut rx = rx.take().unwrap();
-        while let Some(tx) = rx.recv().await {
-            let (task_tx, task_rx) = oneshot::channel();
-            tx.send(task_tx).unwrap();
-            task_rx.await.unwrap();
+        let mut scheduler = Scheduler::new(rx.take().unwrap(), trace.clone(), clock.clone());
+
+        loop {
+            scheduler.start_ready();
+
+            let snapshot = scheduler.snapshot();
+            if snapshot.is_idle && snapshot.pending == 0 {
+                skip.reset();
+            }
+            *shared_state.lock().unwrap() = snapshot.clone();
+            SignalMut::set_if_neq(state, snapshot);
+
+            // Wait for either a new message or a running task to finish.
+            let event = if scheduler.running.is_empty() {
+                Ok(scheduler.rx.recv().await)
+            } else {
+                let done = future::select_all(
+                    scheduler
+                        .running
+                        .iter_mut()
+                        .map(|running| &mut running.done_rx),
+                );
+                match future::select(Box::pin(scheduler.rx.recv()), done).await {
+                    Either::Left((message, _)) => Ok(message),
+                    Either::Right(((result, idx, _), _)) => Err((idx, result)),
+                }
+            };
+
+            match event {
+                Ok(Some(message)) => scheduler.handle(message),
+                Ok(None) => break,
+                Err((idx, result)) => scheduler.finish(idx, result),
+            }
+
+            // Read everything else that's already waiting, so tasks queued together start in
+            // priority order rather than in the order they arrived.
+            while let Ok(message) = scheduler.rx.try_recv() {
+                scheduler.handle(message);
+            }
         }
     });
 
-    use_provider(cx, || Queue { tx: tx.clone() });
+    // Timeouts are measured in app time, so they follow the app's clock.
+    // Wake the scheduler every frame while tasks are running to check for them.
+    use_world(
+        cx,
+        move |time: Res<Time>, queue_trace: Option<Res<QueueTrace>>| {
+            *clock.lock().unwrap() = time.elapsed();
+
+            // Pick up a trace added or removed after the queue was mounted.
+            *trace.lock().unwrap() = queue_trace.map(|queue_trace| queue_trace.clone());
+
+            if !shared_state.lock().unwrap().is_idle {
+                let _ = tx.send(Message::Tick);
+            }
+        },
+    );
+
+    use_provider(cx, || Queue {
+        tx: tx.clone(),
+        state: shared_state.clone(),
+        skip: skip.clone(),
+    });
+}
+
+/// Use the queue's fast-forward switch.
+pub fn use_skip<'a>(cx: ScopeState<'a>) -> &'a Skip {
+    &use_context::<Queue>(cx).unwrap().skip
+}
+
+/// Use the latest snapshot of the queue's state.
+///
+/// Compositions below [`use_queue_provider`] are composed again whenever it changes.
+pub fn use_queue_state(cx: ScopeState) -> QueueState {
+    let queue = use_context::<Queue>(cx).unwrap();
+    queue.state.lock().unwrap().clone()
 }
 
 pub fn use_queued<'a, F: Future>(
     cx: ScopeState<'a>,
+    make_future: impl FnMut() -> F + 'a,
+) -> UseQueued<'a> {
+    use_queued_with_options(cx, TaskOptions::default(), make_future)
+}
+
+pub fn use_queued_with_options<'a, F: Future>(
+    cx: ScopeState<'a>,
+    options: TaskOptions,
     mut make_future: impl FnMut() -> F + 'a,
 ) -> UseQueued<'a> {
+    UseQueued {
+        inner: use_queued_with_abort(cx, options, move |()| make_future(), |()| async {}),
+    }
+}
+
+/// Queue a task that's passed the arguments it was queued with.
+///
+/// This captures state like the current target when the task is queued,
+/// rather than when it starts running.
+pub fn use_queued_with<'a, T: Clone + Send + 'static, F: Future>(
+    cx: ScopeState<'a>,
+    make_future: impl FnMut(T) -> F + 'a,
+) -> UseQueuedWith<'a, T> {
+    use_queued_with_abort(cx, TaskOptions::default(), make_future, |_| async {})
+}
+
+/// Queue a task that runs `on_abort` with the same arguments if it's cancelled while running.
+///
+/// This is used to put animations back into a resting state.
+pub fn use_queued_with_abort<'a, T: Clone + Send + 'static, F: Future, G: Future>(
+    cx: ScopeState<'a>,
+    options: TaskOptions,
+    mut make_future: impl FnMut(T) -> F + 'a,
+    mut on_abort: impl FnMut(T) -> G + 'a,
+) -> UseQueuedWith<'a, T> {
     let queue = use_context::<Queue>(cx).unwrap();
-    let (tx, rx) = use_ref(cx, || {
-        let (tx, rx) = mpsc::unbounded_channel::<oneshot::Sender<()>>();
-        (tx, Cell::new(Some(rx)))
+    let (tx, rx, options_cell) = use_ref(cx, || {
+        let (tx, rx) = mpsc::unbounded_channel::<(oneshot::Sender<()>, QueueHandle, T)>();
+        (tx, Cell::new(Some(rx)), Mutex::new(TaskOptions::default()))
     });
+    *options_cell.lock().unwrap() = options;
 
     use_local_task(cx, move || async move {
         let mut rx = rx.take().unwrap();
-        while let Some(tx) = rx.recv().await {
-            make_future().await;
-            tx.send(()).unwrap();
+        while let Some((tx, handle, args)) = rx.recv().await {
+            if handle.start() {
+                let is_aborted = {
+                    let task = pin!(make_future(args.clone()));
+                    let abort = pin!(handle.state.abort.notified());
+                    matches!(future::select(task, abort).await, Either::Right(_))
+                };
+
+                if is_aborted {
+                    // The queue drops the abort handler too if it gets stuck.
+                    let abort = pin!(on_abort(args));
+                    let kill = pin!(handle.state.kill.notified());
+                    future::select(abort, kill).await;
+                    handle.finish(TaskStatus::Cancelled);
+                } else {
+                    handle.finish(TaskStatus::Finished);
+                }
+            }
+
+            // The provider may have been dropped while this task was running.
+            let _ = tx.send(());
         }
     });
 
-    UseQueued { queue, tx }
+    UseQueuedWith {
+        queue,
+        tx,
+        options: options_cell,
+    }
 }
 
 #[derive(Clone, Copy)]
 pub struct UseQueued<'a> {
-    queue: &'a Queue,
-    tx: &'a mpsc::UnboundedSender<oneshot::Sender<()>>,
+    inner: UseQueuedWith<'a, ()>,
 }
 
 impl UseQueued<'_> {
-    pub fn queue(&self) {
-        self.queue.tx.send(self.tx.clone()).unwrap();
+    pub fn queue(&self) -> QueueHandle {
+        self.inner.queue(())
+    }
+}
+
+pub struct UseQueuedWith<'a, T> {
+    queue: &'a Queue,
+    tx: &'a TaskSender<T>,
+    options: &'a Mutex<TaskOptions>,
+}
+
+impl<T> Clone for UseQueuedWith<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for UseQueuedWith<'_, T> {}
+
+impl<T: Send + 'static> UseQueuedWith<'_, T> {
+    /// Queue this task with `args`.
+    pub fn queue(&self, args: T) -> QueueHandle {
+        let handle = QueueHandle::new(self.queue.tx.clone());
+        let options = self.options.lock().unwrap().clone();
+        let label = options.label.clone();
+
+        let tx = self.tx.clone();
+        let task_handle = handle.clone();
+        let task = Task {
+            options,
+            handle: handle.clone(),
+            queued_at: Instant::now(),
+            start: Box::new(move |done_tx| tx.send((done_tx, task_handle, args)).is_ok()),
+        };
+        if self.queue.tx.send(Message::Queued(task)).is_err() {
+            warn!(
+                "Failed to queue `{}`: the queue provider was dropped",
+                describe(label.as_deref())
+            );
+            handle.finish(TaskStatus::Cancelled);
+        }
+
+        handle
     }
 }
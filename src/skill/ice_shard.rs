@@ -1,90 +1,141 @@
-use super::Skill;
-use crate::character::CharacterState;
+use super::{Cast, Skill};
+use crate::character::{
+    scale_damage, Allegiance, CharacterId, CharacterState, Characters, EffectKind, Stat,
+};
 use actuate::prelude::*;
 use bevy::prelude::*;
 use futures::future;
-use std::f32::consts::{FRAC_PI_2, PI};
 use std::time::Duration;
 
+/// Damage dealt to a target with no resistance.
+const POWER: u32 = 50;
+
+/// Distance from the target the caster stops at to cast.
+const RANGE: f32 = 30.;
+
+/// Energy spent to cast Ice Shard.
+const COST: u32 = 3;
+
+/// Turns the target stays chilled for.
+const CHILL_TURNS: u32 = 2;
+
 #[derive(Data)]
 pub struct IceShard<'a> {
-    pub character_states: SignalMut<'a, Vec<CharacterState>>,
-    pub player_idx: usize,
-    pub target_idx: usize,
+    pub character_states: SignalMut<'a, Characters>,
+    pub player: CharacterId,
+    pub target: Option<CharacterId>,
     pub turn: u32,
-    pub is_turn_done: bool,
+    /// Slot this skill is in on the skill bar.
+    pub slot: usize,
 }
 
 impl Compose for IceShard<'_> {
     fn compose(cx: Scope<Self>) -> impl Compose {
+        let states = cx.me().character_states;
+        let caster = states.get(cx.me().player);
+
+        // Ice Shard can't be cast at the caster's allies.
+        let is_enabled = caster.is_some_and(CharacterState::can_act)
+            && cx
+                .me()
+                .target
+                .and_then(|target| Some((caster?, states.get(target)?)))
+                .is_some_and(|(caster, target)| {
+                    !target.is_defeated() && caster.allegiance(target) != Allegiance::Ally
+                });
+
         Skill {
             name: Cow::Owned(String::from("Ice Shard")),
             description: Cow::Owned(String::from(
-                "Launch a shard of ice at the target, dealing 50 damage.",
+                "Launch a shard of ice at the target, dealing 50 damage reduced by their resistance \
+                 and chilling them for 2 turns.",
             )),
             cooldown: 2,
+            cost: COST,
+            energy: caster.map(|state| state.energy).unwrap_or_default(),
+            caster: cx.me().player,
+            target: cx.me().target,
             turn: cx.me().turn,
-            is_enabled: !cx.me().is_turn_done,
-            on_click: Box::new(move || {
+            slot: cx.me().slot,
+            is_enabled,
+            on_click: Box::new(move |cast: Cast| {
                 Box::pin(async move {
-                    let duration = Duration::from_millis(500);
-                    let arm = 0.5;
-                    let leg = 0.25;
+                    let step = Duration::from_millis(500);
 
-                    let character = cx.me().character_states[cx.me().player_idx].clone();
+                    // Either character may have left the battle since this was queued.
+                    let states = cx.me().character_states;
+                    let (Some(character), Some(target)) =
+                        (states.get(cast.caster).cloned(), states.get(cast.target).cloned())
+                    else {
+                        return false;
+                    };
+
+                    // The target may have been defeated, the caster stunned, or the energy this
+                    // needs spent on another cast while this waited in the queue.
+                    if target.is_defeated() || !character.can_act() || character.energy < COST {
+                        return false;
+                    }
+
+                    // Energy is spent as soon as the cast starts.
+                    SignalMut::update(cx.me().character_states, move |characters| {
+                        if let Some(character_mut) = characters.get_mut(cast.caster) {
+                            character_mut.energy = character_mut.energy.saturating_sub(COST);
+                        }
+                    });
+
+                    // Stop short of wherever the target is standing.
+                    let target_pos = target.translation.get();
+                    let approach = target_pos
+                        - (target_pos - character.home).normalize_or_zero() * RANGE;
+
+                    character
+                        .face(target_pos, Duration::from_millis(250))
+                        .await;
 
                     future::join(
                         character
                             .translation
-                            .animate(Vec3::new(0., 0., -10.), Duration::from_millis(1500)),
+                            .animate(approach, Duration::from_millis(1500)),
                         async {
-                            future::join4(
-                                character.left_arm.animate(arm, duration),
-                                character.right_arm.animate(-arm, duration),
-                                character.left_leg.animate(leg, duration),
-                                character.right_leg.animate(-leg, duration),
-                            )
-                            .await;
-
-                            future::join4(
-                                character.left_arm.animate(-arm, duration),
-                                character.right_arm.animate(arm, duration),
-                                character.left_leg.animate(-leg, duration),
-                                character.right_leg.animate(leg, duration),
-                            )
-                            .await;
-
-                            future::join4(
-                                character.left_arm.animate(0., duration),
-                                character.right_arm.animate(0., duration),
-                                character.left_leg.animate(0., duration),
-                                character.right_leg.animate(0., duration),
-                            )
-                            .await;
-
+                            character.play("walk", step, 1.).await;
                             character
-                                .right_arm
-                                .animate(FRAC_PI_2, Duration::from_millis(200))
+                                .play("cast", Duration::from_millis(200), 1.)
                                 .await;
 
-                            let player_idx = cx.me().player_idx;
-                            let target_idx = cx.me().target_idx;
-                            SignalMut::update(cx.me().character_states, move |characters| {
-                                let character_mut = &mut characters[player_idx];
-                                character_mut.energy -= 1;
+                            // Other casters may have hit the target while this one walked.
+                            let target = cx
+                                .me()
+                                .character_states
+                                .get(cast.target)
+                                .cloned()
+                                .unwrap_or(target);
+                            let damage = scale_damage(
+                                POWER,
+                                character.stat(Stat::Attack),
+                                target.stat(Stat::Resistance),
+                            );
 
-                                let target_character = &mut characters[target_idx];
-                                target_character.health -= 1;
+                            SignalMut::update(cx.me().character_states, move |characters| {
+                                if let Some(target_character) = characters.get_mut(cast.target) {
+                                    target_character.take_damage(damage);
+                                    target_character.apply_effect(EffectKind::Chilled, CHILL_TURNS);
+                                }
                             });
 
-                            character
-                                .right_arm
-                                .animate(0., Duration::from_millis(200))
-                                .await;
+                            future::join(
+                                character.play("lower_arm", Duration::from_millis(200), 1.),
+                                async {
+                                    target.react_to_hit(character.translation.get()).await;
+
+                                    if damage >= target.health + target.shield {
+                                        target.collapse().await;
+                                    }
+                                },
+                            )
+                            .await;
 
                             character
-                                .rotation
-                                .animate(Vec3::new(0., PI, 0.), Duration::from_millis(250))
+                                .face(character.home, Duration::from_millis(250))
                                 .await;
                         },
                     )
@@ -93,38 +144,47 @@ impl Compose for IceShard<'_> {
                     future::join(
                         character
                             .translation
-                            .animate(Vec3::new(0., 0., 40.), Duration::from_millis(1500)),
+                            .animate(character.home, Duration::from_millis(1500)),
                         async {
-                            future::join4(
-                                character.left_arm.animate(arm, duration),
-                                character.right_arm.animate(-arm, duration),
-                                character.left_leg.animate(leg, duration),
-                                character.right_leg.animate(-leg, duration),
-                            )
-                            .await;
-
-                            future::join4(
-                                character.left_arm.animate(-arm, duration),
-                                character.right_arm.animate(arm, duration),
-                                character.left_leg.animate(-leg, duration),
-                                character.right_leg.animate(leg, duration),
-                            )
-                            .await;
-
-                            future::join4(
-                                character.left_arm.animate(0., duration),
-                                character.right_arm.animate(0., duration),
-                                character.left_leg.animate(0., duration),
-                                character.right_leg.animate(0., duration),
-                            )
-                            .await;
+                            character.play("walk", step, 1.).await;
                             character
-                                .rotation
-                                .animate(Vec3::new(0., 0., 0.), Duration::from_millis(250))
+                                .play("face_forward", Duration::from_millis(250), 1.)
                                 .await;
                         },
                     )
                     .await;
+
+                    true
+                })
+            }),
+            on_abort: Box::new(move |cast: Cast| {
+                Box::pin(async move {
+                    let duration = Duration::from_millis(250);
+                    let states = cx.me().character_states;
+                    let character = states.get(cast.caster).cloned();
+                    let target = states.get(cast.target).cloned();
+
+                    future::join(
+                        async {
+                            if let Some(character) = &character {
+                                character.rest(duration).await;
+                            }
+                        },
+                        async {
+                            let Some(target) = &target else {
+                                return;
+                            };
+
+                            // A target defeated before the abort stays down.
+                            if target.is_defeated() {
+                                future::join(target.collapse(), target.flash.animate(0., duration))
+                                    .await;
+                            } else {
+                                target.rest(duration).await;
+                            }
+                        },
+                    )
+                    .await;
                 })
             }),
         }
@@ -1,19 +1,42 @@
-use crate::use_queued;
+use crate::{
+    character::CharacterId,
+    input::{Action, Actions, Rebinding},
+    queue::{use_queue_state, use_queued_with_abort, Lane, QueueHandle, TaskOptions},
+};
 use actuate::prelude::*;
 use bevy::prelude::*;
-use std::{future::Future, pin::Pin};
+use std::{collections::HashMap, future::Future, pin::Pin, sync::Mutex};
 
 mod ice_shard;
 pub use self::ice_shard::IceShard;
 
+/// Caster and target of a skill, captured when it's queued.
+#[derive(Clone, Copy, Debug)]
+pub struct Cast {
+    pub caster: CharacterId,
+    pub target: CharacterId,
+}
+
 #[derive(Data)]
 pub struct Skill<'a> {
     pub name: Cow<'a, String>,
     pub description: Cow<'a, String>,
     pub cooldown: u8,
+    /// Energy the caster spends to use this skill.
+    pub cost: u32,
+    /// Energy the caster has left.
+    pub energy: u32,
+    pub caster: CharacterId,
+    /// Character this skill is used on, if any is targeted.
+    pub target: Option<CharacterId>,
     pub turn: u32,
+    /// Slot this skill is in on the skill bar, for [`Action::Skill`].
+    pub slot: usize,
     pub is_enabled: bool,
-    pub on_click: Box<dyn Fn() -> Pin<Box<dyn Future<Output = ()> + 'a>> + 'a>,
+    /// Cast this skill, returning `false` if it couldn't be cast once its turn in the queue came.
+    pub on_click: Box<dyn Fn(Cast) -> Pin<Box<dyn Future<Output = bool> + 'a>> + 'a>,
+    /// Put the caster and target back to rest after a cast is aborted.
+    pub on_abort: Box<dyn Fn(Cast) -> Pin<Box<dyn Future<Output = ()> + 'a>> + 'a>,
 }
 
 impl Compose for Skill<'_> {
@@ -28,16 +51,99 @@ impl Compose for Skill<'_> {
         let is_hovered = use_mut(&cx, || false);
         let is_pointer_down = use_mut(&cx, || false);
 
-        let task = use_queued(&cx, move || (cx.me().on_click)());
+        // Turn each caster last used this skill on, since the player can switch characters.
+        let last_used = use_mut(&cx, HashMap::<CharacterId, u32>::new);
 
-        let last_used = use_mut(&cx, || None);
+        let queue = use_queue_state(&cx);
+        let task = use_queued_with_abort(
+            &cx,
+            TaskOptions::default()
+                .with_label(cx.me().name.to_string())
+                .with_lane(Lane::Actor(cx.me().caster.into())),
+            move |cast: Cast| async move {
+                // A skill that wasn't cast doesn't go on cooldown.
+                if !(cx.me().on_click)(cast).await {
+                    SignalMut::update(last_used, move |last_used| {
+                        last_used.remove(&cast.caster);
+                    });
+                }
+            },
+            move |cast: Cast| (cx.me().on_abort)(cast),
+        );
+        // Latest cast queued by each caster.
+        let handles = use_ref(&cx, || {
+            Mutex::new(HashMap::<CharacterId, QueueHandle>::new())
+        });
 
         let font = use_world_once(&cx, |asset_server: Res<AssetServer>| {
             asset_server.load("C&C Red Alert [INET].ttf")
         });
 
         let turn = Signal::map(cx.me(), |me| &me.turn);
+        let caster = Signal::map(cx.me(), |me| &me.caster);
+        let target = Signal::map(cx.me(), |me| &me.target);
         let is_enabled = Signal::map(cx.me(), |me| &me.is_enabled);
+        let cost = Signal::map(cx.me(), |me| &me.cost);
+        let energy = Signal::map(cx.me(), |me| &me.energy);
+        let is_affordable = cx.me().energy >= cx.me().cost;
+
+        let activate = move || {
+            if *is_enabled {
+                let mut handles = handles.lock().unwrap();
+                let caster = *caster;
+
+                // Using a skill that's still waiting in the queue withdraws it.
+                if let Some(pending) = handles.remove(&caster).filter(QueueHandle::is_pending) {
+                    pending.cancel();
+                    SignalMut::update(last_used, move |last_used| {
+                        last_used.remove(&caster);
+                    });
+                } else if let Some(target) = *target {
+                    // Skills used while the queue is busy wait their turn.
+                    if *energy >= *cost {
+                        handles.insert(caster, task.queue(Cast { caster, target }));
+
+                        let turn = *turn;
+                        SignalMut::update(last_used, move |last_used| {
+                            last_used.insert(caster, turn);
+                        });
+                    }
+                }
+            }
+        };
+
+        let slot = cx.me().slot;
+        use_world(&cx, move |actions: Res<Actions>| {
+            if actions.just_pressed(Action::Skill(slot)) {
+                activate();
+            }
+        });
+
+        // Right-clicking this skill waits for a key to bind to its slot.
+        let is_rebind_requested = use_mut(&cx, || false);
+        let is_rebinding = use_mut(&cx, || false);
+        let conflict = use_mut(&cx, || None);
+        use_world(&cx, move |mut rebinding: ResMut<Rebinding>| {
+            let action = Action::Skill(slot);
+            if *is_rebind_requested {
+                SignalMut::set(is_rebind_requested, false);
+                rebinding.start(action);
+            }
+
+            let is_waiting = rebinding.action() == Some(action);
+            SignalMut::set_if_neq(is_rebinding, is_waiting);
+            SignalMut::set_if_neq(conflict, rebinding.conflict().filter(|_| is_waiting));
+        });
+
+        let hint = match *conflict {
+            Some((key, action)) => {
+                format!("{key:?} is bound to {action:?}, press it again to move it")
+            }
+            None if *is_rebinding => {
+                String::from("Press a key to bind it, Backspace to clear or Escape to cancel")
+            }
+            None => String::from("Right-click to bind a key"),
+        };
 
         spawn((Node {
             width: Val::Px(4.),
@@ -45,7 +151,7 @@ impl Compose for Skill<'_> {
             ..default()
         },))
         .content((
-            if let Some(last_used) = *last_used {
+            if let Some(last_used) = last_used.get(&cx.me().caster).copied() {
                 let end = last_used + cx.me().cooldown as u32;
 
                 if cx.me().turn < end {
@@ -85,6 +191,37 @@ impl Compose for Skill<'_> {
             } else {
                 None
             },
+            if is_affordable {
+                None
+            } else {
+                Some(
+                    spawn((
+                        Node {
+                            position_type: PositionType::Absolute,
+                            top: Val::Px(0.),
+                            left: Val::Px(0.),
+                            width: Val::Percent(100.),
+                            height: Val::Percent(100.),
+                            align_items: AlignItems::End,
+                            justify_content: JustifyContent::Center,
+                            ..default()
+                        },
+                        BackgroundColor(Color::srgba(0.1, 0.2, 0.6, 0.4)),
+                        PickingBehavior::IGNORE,
+                        ZIndex(4),
+                    ))
+                    .content(spawn((
+                        Text::new("No energy"),
+                        TextColor(Color::srgb_u8(255, 82, 82)),
+                        TextFont {
+                            font: font.clone(),
+                            font_size: 0.75,
+                            ..default()
+                        },
+                        PickingBehavior::IGNORE,
+                    ))),
+                )
+            },
             spawn((
                 Node {
                     position_type: PositionType::Absolute,
@@ -94,7 +231,13 @@ impl Compose for Skill<'_> {
                     height: Val::Px(3.5),
                     ..default()
                 },
-                UiImage::new(icicle.clone()),
+                UiImage::new(icicle.clone()).with_color(
+                    if cx.me().is_enabled && is_affordable && queue.is_idle {
+                        Color::WHITE
+                    } else {
+                        Color::srgb_u8(117, 117, 117)
+                    },
+                ),
                 ZIndex(3),
                 PickingBehavior::IGNORE,
             )),
@@ -113,6 +256,7 @@ impl Compose for Skill<'_> {
                     Color::BLACK
                 }),
                 ZIndex(2),
+                Name::new(cx.me().name.to_string()),
             ))
             .observe(move |_trigger: In<Trigger<Pointer<Over>>>| {
                 if *is_enabled {
@@ -131,16 +275,19 @@ impl Compose for Skill<'_> {
             .observe(move |_trigger: In<Trigger<Pointer<Up>>>| {
                 SignalMut::set(is_pointer_down, false)
             })
-            .observe(move |_trigger: In<Trigger<Pointer<Click>>>| {
-                if *is_enabled {
-                    task.queue();
-                    SignalMut::set(last_used, Some(*turn));
-                }
-            }),
-            if *is_hovered {
+            .observe(
+                move |trigger: In<Trigger<Pointer<Click>>>| match trigger.0.event().button {
+                    PointerButton::Primary => activate(),
+                    PointerButton::Secondary => SignalMut::set(is_rebind_requested, true),
+                    PointerButton::Middle => {}
+                },
+            ),
+            if *is_hovered || *is_rebinding {
                 Some(Menu {
                     name: Signal::map(cx.me(), |me| &*me.name).into(),
                     description: Signal::map(cx.me(), |me| &*me.description).into(),
+                    cost: cx.me().cost,
+                    hint,
                 })
             } else {
                 None
@@ -153,6 +300,9 @@ impl Compose for Skill<'_> {
 struct Menu<'a> {
     name: Cow<'a, String>,
     description: Cow<'a, String>,
+    cost: u32,
+    /// How to bind a key to this skill.
+    hint: String,
 }
 
 impl Compose for Menu<'_> {
@@ -235,6 +385,28 @@ impl Compose for Menu<'_> {
                 ZIndex(1),
                 PickingBehavior::IGNORE,
             )),
+            spawn((
+                Text::new(format!("Costs {} energy", cx.me().cost)),
+                TextColor(Color::srgb_u8(255, 235, 59)),
+                TextFont {
+                    font: font.clone(),
+                    font_size: 1.,
+                    ..default()
+                },
+                ZIndex(1),
+                PickingBehavior::IGNORE,
+            )),
+            spawn((
+                Text::new(cx.me().hint.clone()),
+                TextColor(Color::srgb_u8(189, 189, 189)),
+                TextFont {
+                    font: font.clone(),
+                    font_size: 0.75,
+                    ..default()
+                },
+                ZIndex(1),
+                PickingBehavior::IGNORE,
+            )),
             spawn((
                 Node {
                     position_type: PositionType::Absolute,